@@ -0,0 +1,38 @@
+// Escaping the game and drop names of a large list with and without the cache
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
+use twitch_drops_list::markdown::{escape_markdown, escape_markdown_cached};
+
+// Names as a large response repeats them, once per section, heading and index link
+fn names() -> Vec<String> {
+    let unique: Vec<String> = (0..500)
+        .map(|i| format!("Game_{} [Season *{}*] | Drops #{}", i, i % 7, i))
+        .collect();
+    unique
+        .iter()
+        .cycle()
+        .take(unique.len() * 6)
+        .cloned()
+        .collect()
+}
+
+fn escape(c: &mut Criterion) {
+    let names = names();
+    c.bench_function("escape_markdown", |b| {
+        b.iter(|| {
+            for name in &names {
+                black_box(escape_markdown(black_box(name)));
+            }
+        })
+    });
+    c.bench_function("escape_markdown_cached", |b| {
+        b.iter(|| {
+            for name in &names {
+                black_box(escape_markdown_cached(black_box(name)));
+            }
+        })
+    });
+}
+
+criterion_group!(benches, escape);
+criterion_main!(benches);
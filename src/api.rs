@@ -0,0 +1,541 @@
+//! Types for the drops API response and fetching it
+use chrono::{DateTime, NaiveDateTime, Utc};
+use reqwest::StatusCode;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use serde::de::Unexpected;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::cell::Cell;
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+use unicode_properties::{EmojiStatus, UnicodeEmoji};
+
+use crate::error::{DropsError, Result};
+use crate::retry::Backoff;
+use crate::schema;
+use crate::telemetry;
+
+pub const DROPS_API_URL: &str = "https://twitch-drops-api.sunkwi.com/drops";
+
+// Structs for deserialising API response
+// ApiGame contains the name of the game, how many are watching it and when its data last
+// changed if the API says, and a list of active drop campaigns
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiGame {
+    pub game_display_name: String,
+    #[serde(default)]
+    pub viewer_count: Option<u64>,
+    #[serde(default, deserialize_with = "optional_timestamp")]
+    pub updated_at: Option<DateTime<Utc>>,
+    #[serde(rename = "rewards")]
+    pub drops: Vec<ApiDrops>,
+}
+
+// ApiDrops contains the name of the drop campaign, start and end dates, optional artwork and
+// link to the campaign's page, and a list of rewards
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiDrops {
+    pub name: String,
+    #[serde(deserialize_with = "timestamp")]
+    pub start_at: DateTime<Utc>,
+    #[serde(deserialize_with = "timestamp")]
+    pub end_at: DateTime<Utc>,
+    #[serde(default)]
+    pub image_url: Option<String>,
+    #[serde(default)]
+    pub detail_url: Option<String>,
+    #[serde(rename = "timeBasedDrops")]
+    pub rewards: Vec<ApiReward>,
+}
+
+// ApiReward contains the name of the reward and the number of minutes watched required to earn it
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiReward {
+    pub name: String,
+    #[serde(rename = "requiredMinutesWatched")]
+    pub minutes_required: u16,
+}
+
+thread_local! {
+    // Timestamps without a timezone parsed since parse_response started on a response
+    static NAIVE_TIMESTAMPS: Cell<usize> = const { Cell::new(0) };
+}
+
+// A timestamp with an offset or `Z` suffix, or failing that a naive one such as
+// "2024-06-10T18:00:00", which is taken to be in UTC
+fn timestamp<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<DateTime<Utc>, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    parse_timestamp(&text)
+}
+
+// Like timestamp, for fields that may be missing or null
+fn optional_timestamp<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<DateTime<Utc>>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|text| parse_timestamp(&text))
+        .transpose()
+}
+
+fn parse_timestamp<E: serde::de::Error>(text: &str) -> std::result::Result<DateTime<Utc>, E> {
+    if let Ok(time) = text.parse::<DateTime<Utc>>() {
+        return Ok(time);
+    }
+    let naive = text.parse::<NaiveDateTime>().map_err(|_| {
+        E::invalid_value(
+            Unexpected::Str(text),
+            &"an RFC 3339 timestamp, or one without a timezone",
+        )
+    })?;
+    NAIVE_TIMESTAMPS.with(|count| count.set(count.get() + 1));
+    Ok(naive.and_utc())
+}
+
+// A response wrapped in an object saying when the data was generated, e.g.
+// {"generatedAt": "...", "data": [...]}. The API currently returns the bare list instead
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope {
+    #[serde(
+        default,
+        alias = "lastUpdated",
+        deserialize_with = "optional_timestamp"
+    )]
+    generated_at: Option<DateTime<Utc>>,
+    #[serde(alias = "drops")]
+    data: Vec<ApiGame>,
+}
+
+// Parse a response body into its games and, if it is wrapped in an envelope that says, the
+// time the data was generated
+pub fn parse_response(raw: &str) -> Result<(Vec<ApiGame>, Option<DateTime<Utc>>)> {
+    NAIVE_TIMESTAMPS.with(|count| count.set(0));
+    let parsed = if raw.trim_start().starts_with('{') {
+        serde_json::from_str(raw).map(|envelope: Envelope| (envelope.data, envelope.generated_at))
+    } else {
+        serde_json::from_str(raw).map(|games| (games, None))
+    };
+    let parsed = parsed.map_err(DropsError::parse("json response"))?;
+    let naive = NAIVE_TIMESTAMPS.with(Cell::get);
+    if naive > 0 {
+        eprintln!("{} timestamps have no timezone, assuming UTC", naive);
+    }
+    Ok(parsed)
+}
+
+// A short identifier for a drop campaign derived from its game, name and dates.
+// Uses 64-bit FNV-1a rather than the std hasher so the ID is stable across runs and builds,
+// and changes if any of the components changes
+pub fn drop_id(game: &ApiGame, drop: &ApiDrops) -> String {
+    let mut hash = Fnv1a::new();
+    for part in [
+        game.game_display_name.as_str(),
+        &drop.name,
+        &drop.start_at.to_rfc3339(),
+        &drop.end_at.to_rfc3339(),
+    ] {
+        hash.write(part);
+    }
+    hash.finish()
+}
+
+// Shorten drop and reward names longer than max_len characters, ending them with an ellipsis,
+// so a malformed entry can't blow up the line lengths of any output
+pub fn truncate_names(games: &mut [ApiGame], max_len: usize) {
+    for drop in games.iter_mut().flat_map(|game| &mut game.drops) {
+        truncate_name(&mut drop.name, max_len);
+        for reward in &mut drop.rewards {
+            truncate_name(&mut reward.name, max_len);
+        }
+    }
+}
+
+// Remove emoji from drop and reward names, for consumers that can't lay them out
+pub fn strip_emojis(games: &mut [ApiGame]) {
+    for drop in games.iter_mut().flat_map(|game| &mut game.drops) {
+        strip_emoji(&mut drop.name);
+        for reward in &mut drop.rewards {
+            strip_emoji(&mut reward.name);
+        }
+    }
+}
+
+// Drop the emoji along with the joiners, variation selectors, keycaps and tags that combine
+// them into sequences, then tidy the spaces they leave behind, e.g. "Pack 🎁" becomes "Pack"
+fn strip_emoji(name: &mut String) {
+    let chars: Vec<char> = name.chars().collect();
+    let mut stripped = String::with_capacity(name.len());
+    // The last character when it was stripped, so the parts of its sequence are stripped too
+    let mut stripped_last = None;
+    for (i, &c) in chars.iter().enumerate() {
+        let emoji = match stripped_last {
+            Some(_) if is_sequence_part(c) => true,
+            // Whatever emoji a zero width joiner joins on, even one that defaults to text
+            Some('\u{200d}') => c.is_emoji_char(),
+            _ => is_emoji(c, chars.get(i + 1).copied()),
+        };
+        if emoji {
+            stripped_last = Some(c);
+        } else {
+            stripped_last = None;
+            stripped.push(c);
+        }
+    }
+    if stripped.len() != name.len() {
+        *name = stripped.split_whitespace().collect::<Vec<_>>().join(" ");
+    }
+}
+
+// Whether a character is shown as an emoji going by the Unicode emoji data: characters with
+// Emoji_Presentation always are, the ones that default to text such as ©, ™ or ☑ only when the
+// emoji variation selector follows them. Symbols that aren't emoji at all, like ⌘ ☐ ✓, never are
+fn is_emoji(c: char, next: Option<char>) -> bool {
+    match c.emoji_status() {
+        EmojiStatus::EmojiPresentation
+        | EmojiStatus::EmojiPresentationAndModifierBase
+        | EmojiStatus::EmojiPresentationAndEmojiComponent
+        | EmojiStatus::EmojiPresentationAndModifierAndEmojiComponent => true,
+        EmojiStatus::EmojiModifierBase
+        | EmojiStatus::EmojiOther
+        | EmojiStatus::EmojiOtherAndEmojiComponent => next == Some('\u{fe0f}'),
+        _ => false,
+    }
+}
+
+// The characters that only combine the emoji before them: joiners, variation selectors, the
+// keycap and tags
+fn is_sequence_part(c: char) -> bool {
+    matches!(
+        c,
+        '\u{200d}' | '\u{20e3}' | '\u{fe0e}'..='\u{fe0f}' | '\u{e0020}'..='\u{e007f}'
+    )
+}
+
+// Cut on a character boundary, the ellipsis counting towards the limit
+fn truncate_name(name: &mut String, max_len: usize) {
+    if name.chars().count() <= max_len {
+        return;
+    }
+    let mut truncated: String = name.chars().take(max_len.saturating_sub(1)).collect();
+    truncated.push('…');
+    *name = truncated;
+}
+
+// A hash of everything the rendered list is made from, used by watch mode to skip rendering
+// when nothing changed. Besides the data it covers the labels that change with the time
+// alone, such as "ends in 3 days", which the renderer gives as `labels`
+pub fn fingerprint(games: &[ApiGame], labels: &[String]) -> String {
+    let mut hash = Fnv1a::new();
+    for game in games {
+        hash.write(&game.game_display_name);
+        hash.write(&format!("{:?}", game.viewer_count));
+        hash.write(&format!("{:?}", game.updated_at));
+        for drop in &game.drops {
+            hash.write(&drop_id(game, drop));
+            hash.write(drop.image_url.as_deref().unwrap_or_default());
+            hash.write(drop.detail_url.as_deref().unwrap_or_default());
+            for reward in &drop.rewards {
+                hash.write(&reward.name);
+                hash.write(&reward.minutes_required.to_string());
+            }
+        }
+    }
+    for label in labels {
+        hash.write(label);
+    }
+    hash.finish()
+}
+
+// 64-bit FNV-1a over string parts
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Fnv1a {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, part: &str) {
+        // The separator keeps ("ab", "c") and ("a", "bc") apart
+        for byte in part.bytes().chain([0]) {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> String {
+        format!("{:016x}", self.0)
+    }
+}
+
+// A successful fetch: the raw response body, the parsed games, when the API generated them if
+// it says and how heavy the fetch was
+pub struct Fetched {
+    pub raw: String,
+    pub games: Vec<ApiGame>,
+    pub generated_at: Option<DateTime<Utc>>,
+    pub stats: FetchStats,
+}
+
+// Measurements of a fetch, used for tuning how often to poll
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FetchStats {
+    pub attempts: u32,
+    pub duration_ms: u128,
+    pub bytes: usize,
+    pub games: usize,
+    pub drops: usize,
+    pub rewards: usize,
+}
+
+impl FetchStats {
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let mut contents =
+            serde_json::to_string_pretty(self).map_err(DropsError::serialise("fetch stats"))?;
+        contents.push('\n');
+        fs::write(path, contents).map_err(DropsError::io("write", path))
+    }
+}
+
+// Fetches the list of currently active Twitch Drop campaigns, listed by game name
+// Retries failed attempts up to `retries` times, waiting between them according to `backoff`.
+// With `validate_schema` the response is checked against the bundled schema before parsing
+pub fn fetch_game_data(
+    retries: u32,
+    validate_schema: bool,
+    backoff: &mut Backoff,
+) -> Result<Fetched> {
+    eprintln!("fetching open drop campaigns...");
+
+    let started = Instant::now();
+    let mut attempt = 0;
+    loop {
+        match fetch_once(validate_schema) {
+            Ok((raw, games, generated_at)) => {
+                // The duration includes any retries and the time spent waiting between them
+                let stats = FetchStats {
+                    attempts: attempt + 1,
+                    duration_ms: started.elapsed().as_millis(),
+                    bytes: raw.len(),
+                    games: games.len(),
+                    drops: games.iter().map(|g| g.drops.len()).sum(),
+                    rewards: games
+                        .iter()
+                        .flat_map(|g| &g.drops)
+                        .map(|d| d.rewards.len())
+                        .sum(),
+                };
+                eprintln!(
+                    "fetched {} bytes in {}ms: {} games, {} drops, {} rewards",
+                    stats.bytes, stats.duration_ms, stats.games, stats.drops, stats.rewards
+                );
+                return Ok(Fetched {
+                    raw,
+                    games,
+                    generated_at,
+                    stats,
+                });
+            }
+            Err(DropsError::RateLimited { retry_after }) if attempt < retries => {
+                let delay = backoff.rate_limited_delay(attempt, retry_after);
+                eprintln!(
+                    "rate limited by the api, retrying in {:.1}s...",
+                    delay.as_secs_f64()
+                );
+                thread::sleep(delay);
+                attempt += 1;
+            }
+            Err(err) if attempt < retries => {
+                let delay = backoff.delay(attempt);
+                eprintln!(
+                    "fetch failed ({:#}), retrying in {:.1}s...",
+                    err,
+                    delay.as_secs_f64()
+                );
+                thread::sleep(delay);
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+// Parse a Retry-After header, given either as a number of seconds or as an HTTP date
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    // A date in the past means retrying straight away
+    Some(
+        (date.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+// The error for a 429 Too Many Requests response, with the wait its Retry-After header asks for
+fn rate_limited(status: StatusCode, headers: &HeaderMap, now: DateTime<Utc>) -> Option<DropsError> {
+    (status == StatusCode::TOO_MANY_REQUESTS).then(|| DropsError::RateLimited {
+        retry_after: headers
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| parse_retry_after(value, now)),
+    })
+}
+
+// Fetch the response body once, without retrying or parsing it
+pub fn fetch_body() -> Result<String> {
+    telemetry::span("fetch", |span| {
+        let response = reqwest::blocking::get(DROPS_API_URL)?;
+        if let Some(err) = rate_limited(response.status(), response.headers(), Utc::now()) {
+            return Err(err);
+        }
+        let raw = response.text()?;
+        span.set("bytes", raw.len());
+        Ok(raw)
+    })
+}
+
+fn fetch_once(validate_schema: bool) -> Result<(String, Vec<ApiGame>, Option<DateTime<Utc>>)> {
+    let raw = fetch_body()?;
+    let (games, generated_at) = telemetry::span("parse", |span| {
+        if validate_schema {
+            schema::validate(&raw)?;
+        }
+        let parsed = parse_response(&raw)?;
+        span.set("games", parsed.0.len());
+        Ok::<_, DropsError>(parsed)
+    })?;
+
+    Ok((raw, games, generated_at))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stripped(name: &str) -> String {
+        let mut name = name.to_string();
+        strip_emoji(&mut name);
+        name
+    }
+
+    #[test]
+    fn emoji_and_their_sequences_are_stripped() {
+        assert_eq!(stripped("Pack 🎁"), "Pack");
+        assert_eq!(stripped("❤️ Love"), "Love");
+        assert_eq!(stripped("Flag 🇺🇸 Set"), "Flag Set");
+        assert_eq!(stripped("Family 👩‍👩‍👧 Bundle"), "Family Bundle");
+        assert_eq!(stripped("Wave 👋🏽"), "Wave");
+        assert_eq!(stripped("1️⃣ Pack"), "Pack");
+    }
+
+    #[test]
+    fn symbols_that_are_not_emoji_are_kept() {
+        for name in [
+            "⌘ Command",
+            "☐ Box",
+            "✓ Done",
+            "© Studio ™",
+            "☑ Ticked",
+            "Season 2 #1",
+        ] {
+            assert_eq!(stripped(name), name);
+        }
+    }
+
+    #[test]
+    fn timestamps_with_and_without_a_timezone_parse() {
+        let (games, generated_at) =
+            parse_response(include_str!("../tests/fixtures/drops-timestamps.json")).unwrap();
+        let expected: DateTime<Utc> = "2026-10-10T17:00:00Z".parse().unwrap();
+        assert_eq!(generated_at, Some(expected));
+        assert_eq!(games[0].updated_at, Some(expected));
+        assert_eq!(games[0].drops[0].start_at, expected);
+        assert_eq!(
+            games[0].drops[0].end_at,
+            expected + chrono::Duration::days(14)
+        );
+        assert_eq!(games[1].updated_at, None);
+        assert_eq!(games[1].drops[0].start_at, expected);
+        assert_eq!(NAIVE_TIMESTAMPS.with(Cell::get), 3);
+    }
+
+    #[test]
+    fn invalid_timestamps_are_errors() {
+        let raw = r#"[{"gameDisplayName": "Rust", "updatedAt": "yesterday", "rewards": []}]"#;
+        let err = parse_response(raw).unwrap_err();
+        let source = std::error::Error::source(&err).unwrap().to_string();
+        assert!(source.contains("yesterday"), "{}", source);
+    }
+
+    fn game() -> ApiGame {
+        let (games, _) =
+            parse_response(include_str!("../tests/fixtures/drops-valid.json")).unwrap();
+        games.into_iter().next().unwrap()
+    }
+
+    #[test]
+    fn drop_ids_are_stable_and_follow_the_campaign() {
+        let game = game();
+        let id = drop_id(&game, &game.drops[0]);
+        assert_eq!(id.len(), 16);
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(id, drop_id(&game, &game.drops[0]));
+
+        // Rewards and artwork don't change which campaign it is
+        let mut changed = self::game();
+        changed.drops[0].rewards.clear();
+        changed.drops[0].image_url = Some("https://example.com/new.png".to_string());
+        assert_eq!(id, drop_id(&changed, &changed.drops[0]));
+
+        changed.drops[0].end_at += chrono::Duration::days(1);
+        assert_ne!(id, drop_id(&changed, &changed.drops[0]));
+        let mut renamed = self::game();
+        renamed.game_display_name.push_str(" 2");
+        assert_ne!(id, drop_id(&renamed, &renamed.drops[0]));
+    }
+
+    #[test]
+    fn rate_limited_responses_carry_their_retry_after() {
+        let now = "2026-10-15T00:00:00Z".parse().unwrap();
+        let wait = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(RETRY_AFTER, value.parse().unwrap());
+            match rate_limited(StatusCode::TOO_MANY_REQUESTS, &headers, now) {
+                Some(DropsError::RateLimited { retry_after }) => retry_after,
+                _ => panic!("expected a rate limited error"),
+            }
+        };
+        assert_eq!(wait("120"), Some(Duration::from_secs(120)));
+        assert_eq!(
+            wait("Thu, 15 Oct 2026 00:01:30 GMT"),
+            Some(Duration::from_secs(90))
+        );
+        // A date that already passed means retrying straight away
+        assert_eq!(wait("Wed, 14 Oct 2026 23:00:00 GMT"), Some(Duration::ZERO));
+        assert_eq!(wait("soon"), None);
+        let headers = HeaderMap::new();
+        assert!(rate_limited(StatusCode::OK, &headers, now).is_none());
+        assert!(rate_limited(StatusCode::TOO_MANY_REQUESTS, &headers, now).is_some());
+    }
+
+    #[test]
+    fn long_names_are_cut_short_with_an_ellipsis() {
+        let mut games = vec![game()];
+        games[0].drops[0].name = "é".repeat(1000);
+        games[0].drops[0].rewards[0].name = "Short".to_string();
+        truncate_names(&mut games, 200);
+        let name = &games[0].drops[0].name;
+        assert_eq!(name.chars().count(), 200);
+        assert!(name.ends_with("é…"));
+        assert_eq!(games[0].drops[0].rewards[0].name, "Short");
+    }
+}
@@ -0,0 +1,69 @@
+//! Raw API responses kept on disk so a failed fetch can fall back to the last good one
+use chrono::{DateTime, Utc};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{DropsError, Result};
+
+const PREFIX: &str = "drops-";
+const EXTENSION: &str = "json";
+
+// A previously archived response
+pub struct Archived {
+    pub path: PathBuf,
+    pub raw: String,
+    pub fetched_at: DateTime<Utc>,
+}
+
+// Store a raw response as drops-<timestamp>.json, the timestamps sort chronologically
+pub fn save(dir: &Path, raw: &str, fetched_at: DateTime<Utc>) -> Result<PathBuf> {
+    fs::create_dir_all(dir).map_err(DropsError::io("create", dir))?;
+    let path = dir.join(format!(
+        "{}{}.{}",
+        PREFIX,
+        fetched_at.format("%Y%m%dT%H%M%SZ"),
+        EXTENSION
+    ));
+    fs::write(&path, raw).map_err(DropsError::io("write", &path))?;
+    Ok(path)
+}
+
+// Load the most recent archived response, if there is one
+pub fn latest(dir: &Path) -> Result<Option<Archived>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(DropsError::io("read", dir)(err)),
+    };
+
+    let mut newest: Option<(DateTime<Utc>, PathBuf)> = None;
+    for entry in entries {
+        let path = entry.map_err(DropsError::io("read", dir))?.path();
+        let Some(fetched_at) = archived_at(&path) else {
+            continue;
+        };
+        if newest.as_ref().is_none_or(|(time, _)| fetched_at > *time) {
+            newest = Some((fetched_at, path));
+        }
+    }
+
+    let Some((fetched_at, path)) = newest else {
+        return Ok(None);
+    };
+    let raw = fs::read_to_string(&path).map_err(DropsError::io("read", &path))?;
+    Ok(Some(Archived {
+        path,
+        raw,
+        fetched_at,
+    }))
+}
+
+// Parse the fetch time back out of an archive file name
+fn archived_at(path: &Path) -> Option<DateTime<Utc>> {
+    if path.extension()? != EXTENSION {
+        return None;
+    }
+    let stamp = path.file_stem()?.to_str()?.strip_prefix(PREFIX)?;
+    let naive = chrono::NaiveDateTime::parse_from_str(stamp, "%Y%m%dT%H%M%SZ").ok()?;
+    Some(naive.and_utc())
+}
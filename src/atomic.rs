@@ -0,0 +1,208 @@
+//! Replacing files without leaving them half written
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+use tempfile::NamedTempFile;
+
+use crate::error::{DropsError, Result};
+
+// Write the contents to a temporary file next to the target and rename it over the target.
+// Creating it in the same directory keeps the rename on one filesystem; if the rename still
+// fails the contents are copied to a fresh temporary file there and renamed again. Only when
+// that fails too are they copied straight over the target, which is not atomic, with a warning
+pub fn write_file(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut temp_file = temp_file_for(path)?;
+    temp_file
+        .write_all(contents)
+        .map_err(DropsError::io("write", temp_file.path()))?;
+    persist(temp_file, path)
+}
+
+// Like write_file, gzip-compressing the contents as they are written to the temporary file
+pub fn write_file_gzip(path: &Path, contents: &[u8]) -> Result<()> {
+    let temp_file = temp_file_for(path)?;
+    let temp_path = temp_file.path().to_path_buf();
+    let mut encoder = GzEncoder::new(temp_file, Compression::default());
+    encoder
+        .write_all(contents)
+        .map_err(DropsError::io("write", &temp_path))?;
+    let temp_file = encoder
+        .finish()
+        .map_err(DropsError::io("write", &temp_path))?;
+    persist(temp_file, path)
+}
+
+fn temp_file_for(path: &Path) -> Result<NamedTempFile> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    NamedTempFile::new_in(dir).map_err(DropsError::io("create a temporary file in", dir))
+}
+
+fn persist(temp_file: NamedTempFile, path: &Path) -> Result<()> {
+    let Err(err) = temp_file.persist(path) else {
+        return Ok(());
+    };
+    let temp_path = err.file.path().to_path_buf();
+    let retry = temp_file_for(path).and_then(|copy| {
+        fs::copy(&temp_path, copy.path()).map_err(DropsError::io("write", copy.path()))?;
+        copy.persist(path)
+            .map_err(|err| DropsError::io("rename over", path)(err.error))
+    });
+    if let Err(retry_err) = retry {
+        eprintln!(
+            "warning: could not replace {} atomically ({}; {}), copying over it instead",
+            path.display(),
+            err.error,
+            retry_err
+        );
+        if let Err(source) = fs::copy(&temp_path, path) {
+            return Err(DropsError::Persist {
+                path: path.to_path_buf(),
+                temp_path,
+                rename: err.error,
+                source,
+            });
+        }
+    }
+    Ok(())
+}
+
+// Copy the current contents of a file to <path>.bak, itself replaced atomically so an
+// interrupted run never leaves a partial backup. Returns None if there is nothing to back up
+pub fn backup(path: &Path) -> Result<Option<PathBuf>> {
+    let contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(DropsError::io("read", path)(err)),
+    };
+    let backup_path = with_suffix(path, ".bak");
+    write_file(&backup_path, &contents)?;
+    Ok(Some(backup_path))
+}
+
+// How often a held lock is tried again while waiting for it
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+// An exclusive lock on <path>.lock, released when dropped
+pub struct FileLock {
+    _file: File,
+}
+
+// Lock <path>.lock so overlapping runs take turns writing the file, waiting up to the timeout
+// for another run to finish. The lock file itself is left in place, only the lock is released
+pub fn lock(path: &Path, timeout: Duration) -> Result<FileLock> {
+    let lock_path = with_suffix(path, ".lock");
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .map_err(DropsError::io("open", &lock_path))?;
+    let started = Instant::now();
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Ok(FileLock { _file: file }),
+            Err(TryLockError::WouldBlock) if started.elapsed() < timeout => {
+                thread::sleep(LOCK_POLL_INTERVAL)
+            }
+            Err(TryLockError::WouldBlock) => {
+                return Err(DropsError::Locked {
+                    path: lock_path,
+                    waited: timeout,
+                });
+            }
+            Err(TryLockError::Error(err)) => return Err(DropsError::io("lock", &lock_path)(err)),
+        }
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut with_suffix = path.as_os_str().to_owned();
+    with_suffix.push(suffix);
+    PathBuf::from(with_suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn write_file_replaces_the_contents_without_leaving_temporary_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("DROPS.md");
+        write_file(&path, b"first").unwrap();
+        write_file(&path, b"second").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn write_file_fails_without_touching_a_target_it_cannot_replace() {
+        let dir = tempfile::tempdir().unwrap();
+        // A directory can't be renamed over by a file, so both renames fail and the
+        // error says so instead of leaving the target half written
+        let path = dir.path().join("target");
+        fs::create_dir(&path).unwrap();
+        assert!(write_file(&path, b"contents").is_err());
+        assert!(path.is_dir());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn backup_copies_the_current_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("DROPS.md");
+        assert_eq!(backup(&path).unwrap(), None);
+        fs::write(&path, "old").unwrap();
+        let backup_path = backup(&path).unwrap().unwrap();
+        assert_eq!(backup_path, dir.path().join("DROPS.md.bak"));
+        assert_eq!(fs::read_to_string(backup_path).unwrap(), "old");
+    }
+
+    #[test]
+    fn gzipped_files_decompress_to_the_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("DROPS.json.gz");
+        let contents = "{\"games\": []}\n".repeat(100);
+        write_file_gzip(&path, contents.as_bytes()).unwrap();
+        let mut decompressed = String::new();
+        flate2::read::GzDecoder::new(File::open(&path).unwrap())
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, contents);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn a_second_lock_waits_for_the_first_and_gives_up_after_the_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("DROPS.md");
+        let held = lock(&path, Duration::ZERO).unwrap();
+        let timeout = Duration::from_millis(200);
+        match lock(&path, timeout) {
+            Err(DropsError::Locked {
+                path: locked,
+                waited,
+            }) => {
+                assert_eq!(locked, dir.path().join("DROPS.md.lock"));
+                assert_eq!(waited, timeout);
+            }
+            _ => panic!("expected the second lock to time out"),
+        }
+
+        // Released part way through the wait, so the second run goes ahead
+        let release = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            drop(held);
+        });
+        assert!(lock(&path, Duration::from_secs(10)).is_ok());
+        release.join().unwrap();
+    }
+}
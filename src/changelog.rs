@@ -0,0 +1,86 @@
+//! An append-only log of the campaigns that appeared and ended between runs
+use chrono::{DateTime, Utc};
+use std::collections::BTreeSet;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+use crate::api::{ApiGame, drop_id};
+use crate::error::{DropsError, Result};
+use crate::state::{ActiveDrop, ActiveDrops};
+
+// The campaigns that are still running at the given time
+pub fn active_drops(games: &[ApiGame], now: DateTime<Utc>) -> ActiveDrops {
+    let drops = games
+        .iter()
+        .flat_map(|game| {
+            game.drops
+                .iter()
+                .filter(move |drop| drop.end_at > now)
+                .map(move |drop| ActiveDrop {
+                    id: drop_id(game, drop),
+                    game: game.game_display_name.clone(),
+                    name: drop.name.clone(),
+                    start_at: drop.start_at,
+                    end_at: drop.end_at,
+                })
+        })
+        .collect();
+    ActiveDrops { drops }
+}
+
+// A block with a count line and a + line for every campaign that is new since the previous
+// run and a - line for every one that is no longer active, None if nothing changed
+pub fn entry(previous: &ActiveDrops, current: &ActiveDrops, now: DateTime<Utc>) -> Option<String> {
+    let ids = |drops: &ActiveDrops| -> BTreeSet<String> {
+        drops.drops.iter().map(|drop| drop.id.clone()).collect()
+    };
+    let (before, after) = (ids(previous), ids(current));
+    let appeared: Vec<&ActiveDrop> = current
+        .drops
+        .iter()
+        .filter(|drop| !before.contains(&drop.id))
+        .collect();
+    let ended: Vec<&ActiveDrop> = previous
+        .drops
+        .iter()
+        .filter(|drop| !after.contains(&drop.id))
+        .collect();
+    if appeared.is_empty() && ended.is_empty() {
+        return None;
+    }
+
+    let mut entry = format!(
+        "== {}: {} appeared, {} ended\n",
+        now.format("%Y-%m-%d %H:%M UTC"),
+        appeared.len(),
+        ended.len()
+    );
+    for (sign, drop) in appeared
+        .iter()
+        .map(|drop| ('+', drop))
+        .chain(ended.iter().map(|drop| ('-', drop)))
+    {
+        entry.push_str(&format!(
+            "{} {}: {} ({} to {})\n",
+            sign,
+            drop.game,
+            drop.name,
+            drop.start_at.format("%Y-%m-%d %H:%M"),
+            drop.end_at.format("%Y-%m-%d %H:%M")
+        ));
+    }
+    entry.push('\n');
+    Some(entry)
+}
+
+// Add the entry to the end of the log, creating it if needed. Earlier entries are never touched
+pub fn append(path: &Path, entry: &str) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(DropsError::io("open", path))?;
+    file.write_all(entry.as_bytes())
+        .map_err(DropsError::io("append to", path))
+}
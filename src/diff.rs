@@ -0,0 +1,301 @@
+//! Comparison of two sets of campaigns, e.g. a saved snapshot and the current fetch
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+
+use crate::api::{ApiDrops, ApiGame, drop_id};
+use crate::error::{DropsError, Result};
+
+// A campaign present in both sets, before and after
+struct Changed<'a> {
+    game: &'a str,
+    before: &'a ApiDrops,
+    after: &'a ApiDrops,
+}
+
+// The campaigns that were added, removed, restarted or changed between two sets
+pub struct SnapshotDiff<'a> {
+    added: Vec<(&'a str, &'a ApiDrops)>,
+    removed: Vec<(&'a str, &'a ApiDrops)>,
+    // Relaunched with a later start, same game and name
+    restarted: Vec<Changed<'a>>,
+    changed: Vec<Changed<'a>>,
+}
+
+impl<'a> SnapshotDiff<'a> {
+    // Match the campaigns by their drop ID. The ID covers the dates, so campaigns whose dates
+    // moved are paired up again by game and name: a later start means the campaign was
+    // relaunched and is reported as restarted, other date moves as changed
+    pub fn between(before: &'a [ApiGame], after: &'a [ApiGame]) -> SnapshotDiff<'a> {
+        let before = by_id(before);
+        let after = by_id(after);
+
+        let mut added: Vec<(&str, &ApiDrops)> = after
+            .iter()
+            .filter(|(id, _)| !before.contains_key(*id))
+            .map(|(_, drop)| *drop)
+            .collect();
+        let mut removed: Vec<(&str, &ApiDrops)> = before
+            .iter()
+            .filter(|(id, _)| !after.contains_key(*id))
+            .map(|(_, drop)| *drop)
+            .collect();
+        let mut changed: Vec<Changed> = before
+            .iter()
+            .filter_map(|(id, (game, old))| {
+                let (_, new) = after.get(id)?;
+                Some(Changed {
+                    game,
+                    before: old,
+                    after: new,
+                })
+            })
+            .filter(|change| differs(change.before, change.after))
+            .collect();
+
+        let moved: BTreeSet<(&str, &str)> = removed
+            .iter()
+            .map(|(game, drop)| (*game, drop.name.as_str()))
+            .filter(|key| added.iter().any(|(g, d)| (*g, d.name.as_str()) == *key))
+            .collect();
+        let mut restarted = Vec::new();
+        for &(game, name) in &moved {
+            let take = |drops: &mut Vec<(&'a str, &'a ApiDrops)>| {
+                let i = drops
+                    .iter()
+                    .position(|(g, d)| *g == game && d.name == name)?;
+                Some(drops.remove(i).1)
+            };
+            if let (Some(old), Some(new)) = (take(&mut removed), take(&mut added)) {
+                let pair = Changed {
+                    game,
+                    before: old,
+                    after: new,
+                };
+                if new.start_at > old.start_at {
+                    restarted.push(pair);
+                } else {
+                    changed.push(pair);
+                }
+            }
+        }
+
+        let key = |game: &str, drop: &ApiDrops| (game.to_lowercase(), drop.name.clone());
+        added.sort_by_key(|(game, drop)| key(game, drop));
+        removed.sort_by_key(|(game, drop)| key(game, drop));
+        restarted.sort_by_key(|change| key(change.game, change.after));
+        changed.sort_by_key(|change| key(change.game, change.after));
+        SnapshotDiff {
+            added,
+            removed,
+            restarted,
+            changed,
+        }
+    }
+
+    // Write a plain text report: a count line, then + added, - removed, ^ restarted and
+    // ~ changed lines
+    pub fn write_report(&self, writer: &mut impl Write) -> Result<()> {
+        writeln!(
+            writer,
+            "{} added, {} removed, {} restarted, {} changed",
+            self.added.len(),
+            self.removed.len(),
+            self.restarted.len(),
+            self.changed.len()
+        )
+        .map_err(DropsError::Write)?;
+        for (game, drop) in &self.added {
+            writeln!(writer, "+ {}: {} ({})", game, drop.name, dates(drop))
+                .map_err(DropsError::Write)?;
+        }
+        for (game, drop) in &self.removed {
+            writeln!(writer, "- {}: {} ({})", game, drop.name, dates(drop))
+                .map_err(DropsError::Write)?;
+        }
+        for restart in &self.restarted {
+            writeln!(
+                writer,
+                "^ {}: {} ({}, was {})",
+                restart.game,
+                restart.after.name,
+                dates(restart.after),
+                dates(restart.before)
+            )
+            .map_err(DropsError::Write)?;
+        }
+        for change in &self.changed {
+            writeln!(
+                writer,
+                "~ {}: {}: {}",
+                change.game,
+                change.after.name,
+                describe(change.before, change.after).join("; ")
+            )
+            .map_err(DropsError::Write)?;
+        }
+        Ok(())
+    }
+}
+
+// Every campaign keyed by its drop ID, with the name of its game
+fn by_id(games: &[ApiGame]) -> BTreeMap<String, (&str, &ApiDrops)> {
+    games
+        .iter()
+        .flat_map(|game| {
+            game.drops
+                .iter()
+                .map(move |drop| (drop_id(game, drop), (game.game_display_name.as_str(), drop)))
+        })
+        .collect()
+}
+
+fn differs(before: &ApiDrops, after: &ApiDrops) -> bool {
+    !describe(before, after).is_empty()
+}
+
+// What changed about a campaign, one entry per field
+fn describe(before: &ApiDrops, after: &ApiDrops) -> Vec<String> {
+    let mut changes = Vec::new();
+    if before.start_at != after.start_at {
+        changes.push(format!(
+            "starts {} -> {}",
+            before.start_at.format("%Y-%m-%d %H:%M"),
+            after.start_at.format("%Y-%m-%d %H:%M")
+        ));
+    }
+    if before.end_at != after.end_at {
+        changes.push(format!(
+            "ends {} -> {}",
+            before.end_at.format("%Y-%m-%d %H:%M"),
+            after.end_at.format("%Y-%m-%d %H:%M")
+        ));
+    }
+    let rewards = |drop: &ApiDrops| -> BTreeSet<String> {
+        drop.rewards
+            .iter()
+            .map(|r| format!("{} ({} min)", r.name, r.minutes_required))
+            .collect()
+    };
+    let (old, new) = (rewards(before), rewards(after));
+    let mut reward_changes: Vec<String> = new.difference(&old).map(|r| format!("+{}", r)).collect();
+    reward_changes.extend(old.difference(&new).map(|r| format!("-{}", r)));
+    if !reward_changes.is_empty() {
+        changes.push(format!("rewards {}", reward_changes.join(", ")));
+    }
+    if before.image_url != after.image_url {
+        changes.push("image changed".into());
+    }
+    changes
+}
+
+fn dates(drop: &ApiDrops) -> String {
+    format!(
+        "{} to {}",
+        drop.start_at.format("%Y-%m-%d %H:%M"),
+        drop.end_at.format("%Y-%m-%d %H:%M")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::ApiReward;
+    use chrono::{DateTime, TimeZone, Utc};
+
+    fn date(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, day, 17, 0, 0).unwrap()
+    }
+
+    fn drop(name: &str, start: u32, end: u32, rewards: &[(&str, u16)]) -> ApiDrops {
+        ApiDrops {
+            name: name.to_string(),
+            start_at: date(start),
+            end_at: date(end),
+            image_url: None,
+            detail_url: None,
+            rewards: rewards
+                .iter()
+                .map(|(name, minutes)| ApiReward {
+                    name: name.to_string(),
+                    minutes_required: *minutes,
+                })
+                .collect(),
+        }
+    }
+
+    fn game(name: &str, drops: Vec<ApiDrops>) -> ApiGame {
+        ApiGame {
+            game_display_name: name.to_string(),
+            viewer_count: None,
+            updated_at: None,
+            drops,
+        }
+    }
+
+    fn report(before: &[ApiGame], after: &[ApiGame]) -> String {
+        let mut out = Vec::new();
+        SnapshotDiff::between(before, after)
+            .write_report(&mut out)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn campaigns_are_compared_by_their_drop_id() {
+        let before = vec![game(
+            "Game",
+            vec![
+                drop("Kept", 1, 20, &[("Badge", 60)]),
+                drop("Gone", 1, 20, &[("Badge", 60)]),
+                drop("Rewards", 1, 20, &[("Badge", 60)]),
+            ],
+        )];
+        let after = vec![game(
+            "Game",
+            vec![
+                drop("Kept", 1, 20, &[("Badge", 60)]),
+                drop("Rewards", 1, 20, &[("Badge", 60), ("Emote", 120)]),
+                drop("New", 10, 24, &[("Badge", 60)]),
+            ],
+        )];
+        assert_eq!(
+            report(&before, &after),
+            "1 added, 1 removed, 0 restarted, 1 changed\n\
+             + Game: New (2026-10-10 17:00 to 2026-10-24 17:00)\n\
+             - Game: Gone (2026-10-01 17:00 to 2026-10-20 17:00)\n\
+             ~ Game: Rewards: rewards +Emote (120 min)\n"
+        );
+        assert_eq!(
+            report(&after, &after),
+            "0 added, 0 removed, 0 restarted, 0 changed\n"
+        );
+    }
+
+    #[test]
+    fn extended_campaigns_are_changed_rather_than_replaced() {
+        let before = vec![game("Game", vec![drop("Campaign", 1, 20, &[])])];
+        let after = vec![game("Game", vec![drop("Campaign", 1, 27, &[])])];
+        assert_eq!(
+            report(&before, &after),
+            "0 added, 0 removed, 0 restarted, 1 changed\n\
+             ~ Game: Campaign: ends 2026-10-20 17:00 -> 2026-10-27 17:00\n"
+        );
+    }
+
+    #[test]
+    fn relaunched_campaigns_are_restarted_rather_than_replaced() {
+        let before = vec![game("Game", vec![drop("Season", 1, 10, &[("Badge", 60)])])];
+        let after = vec![game("Game", vec![drop("Season", 12, 26, &[("Badge", 60)])])];
+        assert_eq!(
+            report(&before, &after),
+            "0 added, 0 removed, 1 restarted, 0 changed\n\
+             ^ Game: Season (2026-10-12 17:00 to 2026-10-26 17:00, was 2026-10-01 17:00 to 2026-10-10 17:00)\n"
+        );
+        // The same name in another game is a different campaign
+        let elsewhere = vec![game(
+            "Other",
+            vec![drop("Season", 12, 26, &[("Badge", 60)])],
+        )];
+        assert!(report(&before, &elsewhere).starts_with("1 added, 1 removed, 0 restarted"));
+    }
+}
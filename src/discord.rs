@@ -0,0 +1,220 @@
+//! Discord webhook payloads for the recently started drop campaigns
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::api::ApiGame;
+use crate::error::{DropsError, Result};
+use crate::json;
+use crate::locale::Translations;
+use crate::markdown::{ends_in_days, is_recent};
+
+// Limits imposed by Discord on a single message, lengths are in characters
+const MAX_FIELDS_PER_EMBED: usize = 25;
+const MAX_EMBEDS_PER_MESSAGE: usize = 10;
+const MAX_TITLE_LENGTH: usize = 256;
+const MAX_FIELD_NAME_LENGTH: usize = 256;
+const MAX_FIELD_VALUE_LENGTH: usize = 1024;
+// Summed over the titles and fields of all embeds in a message
+const MAX_MESSAGE_LENGTH: usize = 6000;
+
+// A message that can be POSTed to a webhook as is
+#[derive(Debug, Serialize)]
+pub struct Message {
+    embeds: Vec<Embed>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Embed {
+    title: String,
+    fields: Vec<Field>,
+}
+
+impl Embed {
+    // The characters the embed counts towards the length limit of its message
+    fn length(&self) -> usize {
+        self.title.chars().count() + self.fields.iter().map(Field::length).sum::<usize>()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Field {
+    name: String,
+    value: String,
+    inline: bool,
+}
+
+impl Field {
+    fn length(&self) -> usize {
+        self.name.chars().count() + self.value.chars().count()
+    }
+}
+
+// One embed per game with a field per recently started drop. Titles and fields too long for
+// Discord are cut short, games with more than 25 drops or too much text for one message are
+// split over several embeds, and the embeds over as many messages as needed
+pub fn messages(games: &[ApiGame], now: DateTime<Utc>, text: &Translations) -> Vec<Message> {
+    let mut embeds = Vec::new();
+    for game in games {
+        let title = truncate(&game.game_display_name, MAX_TITLE_LENGTH);
+        let mut embed = Embed {
+            title: title.clone(),
+            fields: Vec::new(),
+        };
+        for drop in game.drops.iter().filter(|d| is_recent(d, now)) {
+            let field = Field {
+                name: truncate(&drop.name, MAX_FIELD_NAME_LENGTH),
+                value: truncate(
+                    &ends_in_days(drop.end_at, now, text),
+                    MAX_FIELD_VALUE_LENGTH,
+                ),
+                inline: false,
+            };
+            if embed.fields.len() == MAX_FIELDS_PER_EMBED
+                || embed.length() + field.length() > MAX_MESSAGE_LENGTH
+            {
+                let full = std::mem::replace(
+                    &mut embed,
+                    Embed {
+                        title: title.clone(),
+                        fields: Vec::new(),
+                    },
+                );
+                embeds.push(full);
+            }
+            embed.fields.push(field);
+        }
+        if !embed.fields.is_empty() {
+            embeds.push(embed);
+        }
+    }
+
+    let mut messages: Vec<Message> = Vec::new();
+    let mut length = 0;
+    for embed in embeds {
+        let embed_length = embed.length();
+        match messages.last_mut() {
+            Some(message)
+                if message.embeds.len() < MAX_EMBEDS_PER_MESSAGE
+                    && length + embed_length <= MAX_MESSAGE_LENGTH =>
+            {
+                message.embeds.push(embed);
+                length += embed_length;
+            }
+            _ => {
+                messages.push(Message {
+                    embeds: vec![embed],
+                });
+                length = embed_length;
+            }
+        }
+    }
+    messages
+}
+
+// Cut text down to at most `max` characters, ending it with an ellipsis when shortened
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut short: String = text.chars().take(max - 1).collect();
+    short.push('…');
+    short
+}
+
+// Render the message payloads as a JSON array, pretty-printed or on a single line
+pub fn render(
+    games: &[ApiGame],
+    now: DateTime<Utc>,
+    text: &Translations,
+    pretty: bool,
+) -> Result<String> {
+    let mut rendered = json::to_string(&messages(games, now, text), pretty)
+        .map_err(DropsError::serialise("discord messages"))?;
+    rendered.push('\n');
+    Ok(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{ApiDrops, ApiReward};
+    use crate::locale::Locale;
+    use chrono::{Duration, TimeZone};
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 15, 0, 0, 0).unwrap()
+    }
+
+    fn game(name: &str, drops: Vec<(String, i64)>) -> ApiGame {
+        ApiGame {
+            game_display_name: name.to_string(),
+            viewer_count: None,
+            updated_at: None,
+            drops: drops
+                .into_iter()
+                .map(|(name, started)| ApiDrops {
+                    name,
+                    start_at: now() - Duration::days(started),
+                    end_at: now() + Duration::days(3),
+                    image_url: None,
+                    detail_url: None,
+                    rewards: vec![ApiReward {
+                        name: "Badge".to_string(),
+                        minutes_required: 60,
+                    }],
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn only_recently_started_drops_get_a_field() {
+        let games = [game(
+            "Game",
+            vec![("New".to_string(), 1), ("Old".to_string(), 30)],
+        )];
+        let messages = messages(&games, now(), Locale::En.translations());
+        assert_eq!(messages.len(), 1);
+        let fields = &messages[0].embeds[0].fields;
+        assert_eq!(fields.len(), 1);
+        assert_eq!(fields[0].name, "New");
+    }
+
+    #[test]
+    fn long_titles_and_field_names_are_cut_short() {
+        let games = [game(&"g".repeat(300), vec![("d".repeat(300), 1)])];
+        let messages = messages(&games, now(), Locale::En.translations());
+        let embed = &messages[0].embeds[0];
+        assert_eq!(embed.title.chars().count(), MAX_TITLE_LENGTH);
+        assert!(embed.title.ends_with('…'));
+        assert_eq!(embed.fields[0].name.chars().count(), MAX_FIELD_NAME_LENGTH);
+    }
+
+    #[test]
+    fn messages_stay_within_the_length_and_count_limits() {
+        let games: Vec<ApiGame> = (0..12)
+            .map(|i| {
+                let drops = (0..30).map(|j| (format!("{}{}", j, "d".repeat(250)), 1));
+                game(&format!("Game {}", i), drops.collect())
+            })
+            .collect();
+        let messages = messages(&games, now(), Locale::En.translations());
+        let fields: usize = messages
+            .iter()
+            .flat_map(|m| &m.embeds)
+            .map(|e| e.fields.len())
+            .sum();
+        assert_eq!(fields, 12 * 30);
+        for message in &messages {
+            assert!(message.embeds.len() <= MAX_EMBEDS_PER_MESSAGE);
+            let length: usize = message.embeds.iter().map(Embed::length).sum();
+            assert!(length <= MAX_MESSAGE_LENGTH);
+            assert!(
+                message
+                    .embeds
+                    .iter()
+                    .all(|e| e.fields.len() <= MAX_FIELDS_PER_EMBED)
+            );
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Errors returned by the library, so embedders can tell a network failure from a parse failure
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, DropsError>;
+
+#[derive(Debug, Error)]
+pub enum DropsError {
+    // The request to the drops API failed or its body could not be read
+    #[error("failed to fetch from api")]
+    Fetch(#[from] reqwest::Error),
+
+    // The drops API answered 429 Too Many Requests, with the wait its Retry-After header asked for
+    #[error("rate limited by the api")]
+    RateLimited { retry_after: Option<Duration> },
+
+    // A response or file on disk is not the JSON we expect
+    #[error("failed to parse {what}")]
+    Parse {
+        what: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    // The response parsed as JSON but breaks the bundled schema
+    #[error("api response does not match the schema:\n{0}")]
+    Schema(String),
+
+    // A TOML file given on the command line is malformed
+    #[error("failed to parse {}", path.display())]
+    Toml {
+        path: PathBuf,
+        #[source]
+        source: toml::de::Error,
+    },
+
+    #[error("failed to serialise {what}")]
+    Serialise {
+        what: &'static str,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    // Reading, writing or creating a file or directory failed
+    #[error("failed to {op} {}", path.display())]
+    Io {
+        op: &'static str,
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    // A finished temporary file could neither be renamed nor copied over its target
+    #[error(
+        "failed to replace {}: renaming {} failed ({}) and copying it failed too",
+        path.display(),
+        temp_path.display(),
+        rename
+    )]
+    Persist {
+        path: PathBuf,
+        temp_path: PathBuf,
+        rename: io::Error,
+        #[source]
+        source: io::Error,
+    },
+
+    // Another run held the lock on the output for longer than we were willing to wait
+    #[error("{} is held by another run, gave up after {}s", path.display(), waited.as_secs())]
+    Locked { path: PathBuf, waited: Duration },
+
+    // Writing rendered output to the writer failed
+    #[error("failed to write output")]
+    Write(#[source] io::Error),
+
+    // Drawing the interactive browser or reading a key press for it failed
+    #[error("failed to {op} the terminal")]
+    Terminal {
+        op: &'static str,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("{0}")]
+    Template(String),
+
+    #[error("unknown field `{field}`, expected one of: {expected}")]
+    UnknownField { field: String, expected: String },
+}
+
+impl DropsError {
+    // For map_err on file operations, e.g. fs::read(path).map_err(DropsError::io("read", path))
+    pub fn io<'a>(op: &'static str, path: &'a Path) -> impl FnOnce(io::Error) -> DropsError + 'a {
+        move |source| DropsError::Io {
+            op,
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    pub fn parse(what: impl Into<String>) -> impl FnOnce(serde_json::Error) -> DropsError {
+        let what = what.into();
+        move |source| DropsError::Parse { what, source }
+    }
+
+    pub fn serialise(what: &'static str) -> impl FnOnce(serde_json::Error) -> DropsError {
+        move |source| DropsError::Serialise { what, source }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::ErrorKind;
+
+    #[test]
+    fn file_errors_name_the_operation_and_path() {
+        let err = DropsError::io("read", Path::new("drops-known.json"))(io::Error::from(
+            ErrorKind::NotFound,
+        ));
+        assert_eq!(err.to_string(), "failed to read drops-known.json");
+    }
+
+    #[test]
+    fn output_errors_keep_their_cause() {
+        let err = DropsError::Write(io::Error::from(ErrorKind::BrokenPipe));
+        assert_eq!(err.to_string(), "failed to write output");
+        assert!(std::error::Error::source(&err).is_some());
+    }
+}
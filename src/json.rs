@@ -0,0 +1,213 @@
+//! JSON output of the active drop campaigns
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+use crate::api::{ApiDrops, ApiGame, drop_id};
+use crate::error::{DropsError, Result};
+use crate::summary::Summary;
+
+// Field names accepted by --fields, in the order they appear in a record
+pub const FIELDS: &[&str] = &[
+    "game", "dropName", "startAt", "endAt", "imageUrl", "rewards",
+];
+
+// One drop campaign with its game, flattened out of the nested API response
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DropRecord<'a> {
+    game: &'a str,
+    drop_name: &'a str,
+    start_at: DateTime<Utc>,
+    end_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image_url: Option<&'a str>,
+    rewards: Vec<RewardRecord<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RewardRecord<'a> {
+    name: &'a str,
+    minutes_required: u16,
+}
+
+// Check that every requested field exists so typos fail before anything is fetched
+pub fn validate_fields(fields: &[String]) -> Result<()> {
+    for field in fields {
+        if !FIELDS.contains(&field.as_str()) {
+            return Err(DropsError::UnknownField {
+                field: field.clone(),
+                expected: FIELDS.join(", "),
+            });
+        }
+    }
+    Ok(())
+}
+
+// Flatten the games into one record per drop campaign
+pub fn drop_records(games: &[ApiGame]) -> Vec<DropRecord<'_>> {
+    games
+        .iter()
+        .flat_map(|game| game.drops.iter().map(|drop| drop_record(game, drop)))
+        .collect()
+}
+
+fn drop_record<'a>(game: &'a ApiGame, drop: &'a ApiDrops) -> DropRecord<'a> {
+    DropRecord {
+        game: &game.game_display_name,
+        drop_name: &drop.name,
+        start_at: drop.start_at,
+        end_at: drop.end_at,
+        image_url: drop.image_url.as_deref(),
+        rewards: drop
+            .rewards
+            .iter()
+            .map(|reward| RewardRecord {
+                name: &reward.name,
+                minutes_required: reward.minutes_required,
+            })
+            .collect(),
+    }
+}
+
+// Serialise a record, keeping only the requested fields when a projection is given
+pub fn project(record: &DropRecord, fields: &[String]) -> Result<Value> {
+    let mut value = serde_json::to_value(record).map_err(DropsError::serialise("drop"))?;
+    if !fields.is_empty()
+        && let Value::Object(map) = &mut value
+    {
+        map.retain(|key, _| fields.iter().any(|field| field == key));
+    }
+    Ok(value)
+}
+
+// Render one compact JSON object per line and drop campaign, with the seconds left until it ends
+pub fn render_lines(games: &[ApiGame], fields: &[String], now: DateTime<Utc>) -> Result<String> {
+    let mut rendered = String::new();
+    for record in drop_records(games) {
+        let mut value = project(&record, fields)?;
+        if let Value::Object(map) = &mut value {
+            map.insert(
+                "endsInSeconds".to_string(),
+                Value::from((record.end_at - now).num_seconds()),
+            );
+        }
+        rendered.push_str(&serde_json::to_string(&value).map_err(DropsError::serialise("drop"))?);
+        rendered.push('\n');
+    }
+    Ok(rendered)
+}
+
+// Render every drop campaign as a JSON array, pretty-printed or on a single line
+pub fn render(games: &[ApiGame], fields: &[String], pretty: bool) -> Result<String> {
+    let records = drop_records(games)
+        .iter()
+        .map(|record| project(record, fields))
+        .collect::<Result<Vec<_>>>()?;
+    let mut rendered = to_string(&records, pretty).map_err(DropsError::serialise("drops"))?;
+    rendered.push('\n');
+    Ok(rendered)
+}
+
+// Render the drop campaigns whose ID is in `ids` as a JSON array, each record with its ID
+pub fn render_new(games: &[ApiGame], ids: &BTreeSet<String>, pretty: bool) -> Result<String> {
+    let mut records = Vec::new();
+    for game in games {
+        for drop in &game.drops {
+            let id = drop_id(game, drop);
+            if !ids.contains(&id) {
+                continue;
+            }
+            let mut value = project(&drop_record(game, drop), &[])?;
+            if let Value::Object(map) = &mut value {
+                map.insert("id".to_string(), Value::from(id));
+            }
+            records.push(value);
+        }
+    }
+    let mut rendered = to_string(&records, pretty).map_err(DropsError::serialise("new drops"))?;
+    rendered.push('\n');
+    Ok(rendered)
+}
+
+// Render the headline numbers as a single JSON object
+pub fn render_summary(summary: &Summary, pretty: bool) -> Result<String> {
+    let mut rendered = to_string(summary, pretty).map_err(DropsError::serialise("summary"))?;
+    rendered.push('\n');
+    Ok(rendered)
+}
+
+// Serialise a value either pretty-printed or compact
+pub fn to_string(value: &impl Serialize, pretty: bool) -> serde_json::Result<String> {
+    if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::ApiReward;
+    use chrono::TimeZone;
+
+    fn game() -> ApiGame {
+        ApiGame {
+            game_display_name: "Game".to_string(),
+            viewer_count: None,
+            updated_at: None,
+            drops: vec![ApiDrops {
+                name: "Campaign".to_string(),
+                start_at: Utc.with_ymd_and_hms(2026, 10, 10, 0, 0, 0).unwrap(),
+                end_at: Utc.with_ymd_and_hms(2026, 10, 20, 0, 0, 0).unwrap(),
+                image_url: Some("https://example.com/box.png".to_string()),
+                detail_url: None,
+                rewards: vec![ApiReward {
+                    name: "Badge".to_string(),
+                    minutes_required: 60,
+                }],
+            }],
+        }
+    }
+
+    fn fields(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected_with_the_valid_ones() {
+        assert!(validate_fields(&fields(&["game", "endAt"])).is_ok());
+        let err = validate_fields(&fields(&["game", "bogus"])).unwrap_err();
+        assert!(matches!(&err, DropsError::UnknownField { field, .. } if field == "bogus"));
+        assert_eq!(
+            err.to_string(),
+            "unknown field `bogus`, expected one of: game, dropName, startAt, endAt, imageUrl, rewards"
+        );
+    }
+
+    #[test]
+    fn fields_keep_only_the_requested_keys_in_record_order() {
+        let games = [game()];
+        let fields = fields(&["game", "dropName", "endAt"]);
+        assert_eq!(
+            render(&games, &fields, false).unwrap(),
+            "[{\"game\":\"Game\",\"dropName\":\"Campaign\",\"endAt\":\"2026-10-20T00:00:00Z\"}]\n"
+        );
+        let now = Utc.with_ymd_and_hms(2026, 10, 19, 0, 0, 0).unwrap();
+        assert_eq!(
+            render_lines(&games, &fields, now).unwrap(),
+            "{\"game\":\"Game\",\"dropName\":\"Campaign\",\"endAt\":\"2026-10-20T00:00:00Z\",\"endsInSeconds\":86400}\n"
+        );
+    }
+
+    #[test]
+    fn no_fields_keeps_the_whole_record() {
+        let rendered = render(&[game()], &[], false).unwrap();
+        assert!(rendered.contains("\"startAt\""));
+        assert!(rendered.contains("\"imageUrl\""));
+        assert!(rendered.contains("\"minutesRequired\":60"));
+    }
+}
@@ -0,0 +1,32 @@
+//! Fetch active Twitch Drop campaigns and render them as markdown or JSON
+pub mod api;
+pub mod archive;
+pub mod atomic;
+pub mod changelog;
+pub mod diff;
+pub mod discord;
+pub mod error;
+pub mod json;
+pub mod locale;
+pub mod manifest;
+pub mod markdown;
+pub mod merge;
+pub mod preview;
+pub mod probe;
+pub mod query;
+pub mod retry;
+pub mod reward;
+pub mod schema;
+pub mod shields;
+pub mod state;
+pub mod summary;
+pub mod telemetry;
+pub mod template;
+#[cfg(feature = "tui")]
+pub mod tui;
+
+pub use api::{
+    ApiDrops, ApiGame, ApiReward, FetchStats, Fetched, drop_id, fetch_body, fetch_game_data,
+    fingerprint, parse_response, strip_emojis, truncate_names,
+};
+pub use error::{DropsError, Result};
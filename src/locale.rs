@@ -0,0 +1,342 @@
+//! Built-in translations for the text written to the drops list
+use crate::reward::RewardKind;
+
+// Languages with a built-in translation table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    En,
+    De,
+    Fr,
+}
+
+impl Locale {
+    // Parse a locale tag such as "de" or "fr-FR", returning None for unsupported languages
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        let language = tag.split(['-', '_']).next().unwrap_or_default();
+        match language.to_lowercase().as_str() {
+            "en" => Some(Locale::En),
+            "de" => Some(Locale::De),
+            "fr" => Some(Locale::Fr),
+            _ => None,
+        }
+    }
+
+    pub fn translations(self) -> &'static Translations {
+        match self {
+            Locale::En => &EN,
+            Locale::De => &DE,
+            Locale::Fr => &FR,
+        }
+    }
+}
+
+// Text used in the rendered output. `{}` in a template is replaced with a value
+pub struct Translations {
+    pub title: &'static str,
+    pub top_rewards_title: &'static str,
+    pub digest_title: &'static str,
+    pub digest_week: &'static str,
+    pub digest_started: &'static str,
+    pub digest_ending: &'static str,
+    pub digest_top_games: &'static str,
+    pub digest_campaigns: &'static str,
+    pub latest_heading: &'static str,
+    pub all_heading: &'static str,
+    pub favorites_heading: &'static str,
+    pub deadlines_heading: &'static str,
+    pub index_heading: &'static str,
+    pub end_week_heading: &'static str,
+    pub rewardless_heading: &'static str,
+    pub no_rewardless: &'static str,
+    pub table_game: &'static str,
+    pub table_campaign: &'static str,
+    pub table_ends: &'static str,
+    pub table_rewards: &'static str,
+    pub table_max_watch_time: &'static str,
+    pub longest_heading: &'static str,
+    pub shortest_heading: &'static str,
+    pub no_campaigns: &'static str,
+    pub summary: &'static str,
+    pub no_rewards: &'static str,
+    pub totals: &'static str,
+    pub no_recent_campaigns: &'static str,
+    // Notes under all drops for the campaigns only listed in the latest drops section, for when
+    // that section comes before or after it
+    pub recent_above: &'static str,
+    pub recent_below: &'static str,
+    pub recent_today: &'static str,
+    pub recent_yesterday: &'static str,
+    pub today: &'static str,
+    pub tomorrow: &'static str,
+    pub in_days: &'static str,
+    pub ends: &'static str,
+    pub ended_today: &'static str,
+    pub ended_yesterday: &'static str,
+    pub ended_days_ago: &'static str,
+    pub starts: &'static str,
+    pub started_today: &'static str,
+    pub started_yesterday: &'static str,
+    pub started_days_ago: &'static str,
+    pub minutes_watched: &'static str,
+    pub hours_watched: &'static str,
+    pub watching: &'static str,
+    pub updated_ago: &'static str,
+    pub more_rewards: &'static str,
+    pub span_days: &'static str,
+    pub span_hours: &'static str,
+    pub kind_emote: &'static str,
+    pub kind_badge: &'static str,
+    pub kind_in_game_item: &'static str,
+    pub kind_other: &'static str,
+    pub date_format: &'static str,
+    // None leaves numbers ungrouped
+    pub thousands_separator: Option<char>,
+    pub decimal_separator: char,
+}
+
+impl Translations {
+    // Label for a kind of reward
+    pub fn reward_kind(&self, kind: RewardKind) -> &'static str {
+        match kind {
+            RewardKind::Emote => self.kind_emote,
+            RewardKind::Badge => self.kind_badge,
+            RewardKind::InGameItem => self.kind_in_game_item,
+            RewardKind::Other => self.kind_other,
+        }
+    }
+
+    // Substitute a value into one of the templates above
+    pub fn fill(&self, template: &str, value: impl ToString) -> String {
+        template.replacen("{}", &value.to_string(), 1)
+    }
+
+    // Format a whole number with the digit grouping used by this language
+    pub fn number(&self, value: u64) -> String {
+        let digits = value.to_string();
+        let Some(separator) = self.thousands_separator else {
+            return digits;
+        };
+        let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                grouped.push(separator);
+            }
+            grouped.push(c);
+        }
+        grouped
+    }
+
+    // Format a number with one decimal place and the decimal separator of this language
+    pub fn decimal(&self, value: f64) -> String {
+        format!("{:.1}", value).replace('.', &self.decimal_separator.to_string())
+    }
+}
+
+static EN: Translations = Translations {
+    title: "Twitch Drops Campaigns",
+    top_rewards_title: "Top Twitch Drops Rewards",
+    digest_title: "Weekly Twitch Drops digest",
+    digest_week: "Week of {}",
+    digest_started: "{} campaigns started this week",
+    digest_ending: "{} campaigns end this week",
+    digest_top_games: "Games with the most campaigns:",
+    digest_campaigns: "{} campaigns",
+    latest_heading: "Latest drops",
+    all_heading: "All drops",
+    favorites_heading: "Favorites",
+    deadlines_heading: "Next deadlines",
+    index_heading: "Index",
+    end_week_heading: "Drops by week of ending",
+    rewardless_heading: "Drops without rewards",
+    no_rewardless: "Every active campaign has rewards.",
+    table_game: "Game",
+    table_campaign: "Campaign",
+    table_ends: "Ends",
+    table_rewards: "Rewards",
+    table_max_watch_time: "Max Watch Time",
+    longest_heading: "Longest running drops",
+    shortest_heading: "Shortest running drops",
+    no_campaigns: "No active drops campaigns found.",
+    summary: "{} campaigns across {} games, {} ending within 24 hours",
+    no_rewards: "No rewards found in active drops campaigns.",
+    totals: "{} rewards and {} hours of watch time in total.",
+    no_recent_campaigns: "No drop campaigns started in the last {} days.",
+    recent_above: "Campaigns that started in the last {} days are only listed above, under {}.",
+    recent_below: "Campaigns that started in the last {} days are only listed below, under {}.",
+    recent_today: "Today",
+    recent_yesterday: "Yesterday",
+    today: "today",
+    tomorrow: "tomorrow",
+    in_days: "in {} days",
+    ends: "ends {}",
+    ended_today: "ended today",
+    ended_yesterday: "ended yesterday",
+    ended_days_ago: "ended {} days ago",
+    starts: "starts {}",
+    started_today: "started today",
+    started_yesterday: "started yesterday",
+    started_days_ago: "started {} days ago",
+    minutes_watched: "{} minutes watched",
+    hours_watched: "{} hours watched",
+    watching: "{} watching",
+    updated_ago: "updated {} ago",
+    more_rewards: "...and {} more",
+    span_days: "runs for {} days",
+    span_hours: "runs for {} hours",
+    kind_emote: "Emote",
+    kind_badge: "Badge",
+    kind_in_game_item: "In-game item",
+    kind_other: "Other",
+    date_format: "%Y-%m-%d",
+    thousands_separator: None,
+    decimal_separator: '.',
+};
+
+static DE: Translations = Translations {
+    title: "Twitch-Drops-Kampagnen",
+    top_rewards_title: "Die besten Twitch-Drops-Belohnungen",
+    digest_title: "Wöchentliche Twitch-Drops-Übersicht",
+    digest_week: "Woche vom {}",
+    digest_started: "{} Kampagnen haben diese Woche begonnen",
+    digest_ending: "{} Kampagnen enden diese Woche",
+    digest_top_games: "Spiele mit den meisten Kampagnen:",
+    digest_campaigns: "{} Kampagnen",
+    latest_heading: "Neueste Drops",
+    all_heading: "Alle Drops",
+    favorites_heading: "Favoriten",
+    deadlines_heading: "Nächste Fristen",
+    index_heading: "Index",
+    end_week_heading: "Drops nach Endwoche",
+    rewardless_heading: "Drops ohne Belohnungen",
+    no_rewardless: "Alle aktiven Kampagnen haben Belohnungen.",
+    table_game: "Spiel",
+    table_campaign: "Kampagne",
+    table_ends: "Endet",
+    table_rewards: "Belohnungen",
+    table_max_watch_time: "Max. Zuschauzeit",
+    longest_heading: "Am längsten laufende Drops",
+    shortest_heading: "Am kürzesten laufende Drops",
+    no_campaigns: "Keine aktiven Drop-Kampagnen gefunden.",
+    summary: "{} Kampagnen in {} Spielen, {} enden innerhalb von 24 Stunden",
+    no_rewards: "Keine Belohnungen in aktiven Drop-Kampagnen gefunden.",
+    totals: "Insgesamt {} Belohnungen und {} Stunden Zuschauzeit.",
+    no_recent_campaigns: "In den letzten {} Tagen wurden keine Drop-Kampagnen gestartet.",
+    recent_above: "Kampagnen, die in den letzten {} Tagen gestartet sind, stehen nur weiter oben unter {}.",
+    recent_below: "Kampagnen, die in den letzten {} Tagen gestartet sind, stehen nur weiter unten unter {}.",
+    recent_today: "Heute",
+    recent_yesterday: "Gestern",
+    today: "heute",
+    tomorrow: "morgen",
+    in_days: "in {} Tagen",
+    ends: "endet {}",
+    ended_today: "heute beendet",
+    ended_yesterday: "gestern beendet",
+    ended_days_ago: "vor {} Tagen beendet",
+    starts: "beginnt {}",
+    started_today: "heute gestartet",
+    started_yesterday: "gestern gestartet",
+    started_days_ago: "vor {} Tagen gestartet",
+    minutes_watched: "{} Minuten zugeschaut",
+    hours_watched: "{} Stunden zugeschaut",
+    watching: "{} Zuschauer",
+    updated_ago: "vor {} aktualisiert",
+    more_rewards: "...und {} weitere",
+    span_days: "läuft {} Tage",
+    span_hours: "läuft {} Stunden",
+    kind_emote: "Emote",
+    kind_badge: "Abzeichen",
+    kind_in_game_item: "Ingame-Gegenstand",
+    kind_other: "Sonstiges",
+    date_format: "%d.%m.%Y",
+    thousands_separator: Some('.'),
+    decimal_separator: ',',
+};
+
+static FR: Translations = Translations {
+    title: "Campagnes de drops Twitch",
+    top_rewards_title: "Meilleures récompenses de drops Twitch",
+    digest_title: "Résumé hebdomadaire des drops Twitch",
+    digest_week: "Semaine du {}",
+    digest_started: "{} campagnes ont commencé cette semaine",
+    digest_ending: "{} campagnes se terminent cette semaine",
+    digest_top_games: "Jeux avec le plus de campagnes :",
+    digest_campaigns: "{} campagnes",
+    latest_heading: "Derniers drops",
+    all_heading: "Tous les drops",
+    favorites_heading: "Favoris",
+    deadlines_heading: "Prochaines échéances",
+    index_heading: "Index",
+    end_week_heading: "Drops par semaine de fin",
+    rewardless_heading: "Drops sans récompenses",
+    no_rewardless: "Toutes les campagnes actives ont des récompenses.",
+    table_game: "Jeu",
+    table_campaign: "Campagne",
+    table_ends: "Fin",
+    table_rewards: "Récompenses",
+    table_max_watch_time: "Visionnage max.",
+    longest_heading: "Drops les plus longs",
+    shortest_heading: "Drops les plus courts",
+    no_campaigns: "Aucune campagne de drops active trouvée.",
+    summary: "{} campagnes dans {} jeux, {} se terminent dans les 24 heures",
+    no_rewards: "Aucune récompense trouvée dans les campagnes de drops actives.",
+    totals: "{} récompenses et {} heures de visionnage au total.",
+    no_recent_campaigns: "Aucune campagne de drops lancée au cours des {} derniers jours.",
+    recent_above: "Les campagnes lancées au cours des {} derniers jours figurent uniquement plus haut, sous {}.",
+    recent_below: "Les campagnes lancées au cours des {} derniers jours figurent uniquement plus bas, sous {}.",
+    recent_today: "Aujourd'hui",
+    recent_yesterday: "Hier",
+    today: "aujourd'hui",
+    tomorrow: "demain",
+    in_days: "dans {} jours",
+    ends: "se termine {}",
+    ended_today: "terminée aujourd'hui",
+    ended_yesterday: "terminée hier",
+    ended_days_ago: "terminée il y a {} jours",
+    starts: "commence {}",
+    started_today: "lancée aujourd'hui",
+    started_yesterday: "lancée hier",
+    started_days_ago: "lancée il y a {} jours",
+    minutes_watched: "{} minutes regardées",
+    hours_watched: "{} heures regardées",
+    watching: "{} spectateurs",
+    updated_ago: "mis à jour il y a {}",
+    more_rewards: "...et {} de plus",
+    span_days: "dure {} jours",
+    span_hours: "dure {} heures",
+    kind_emote: "Emote",
+    kind_badge: "Badge",
+    kind_in_game_item: "Objet en jeu",
+    kind_other: "Autre",
+    date_format: "%d/%m/%Y",
+    thousands_separator: Some('\u{202f}'),
+    decimal_separator: ',',
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn english_numbers_are_not_grouped() {
+        let text = Locale::En.translations();
+        assert_eq!(text.number(1200), "1200");
+        assert_eq!(text.decimal(1.5), "1.5");
+    }
+
+    #[test]
+    fn numbers_use_the_separators_of_the_language() {
+        let de = Locale::De.translations();
+        assert_eq!(de.number(1234567), "1.234.567");
+        assert_eq!(de.decimal(1.5), "1,5");
+        let fr = Locale::Fr.translations();
+        assert_eq!(fr.number(1200), "1\u{202f}200");
+        assert_eq!(fr.decimal(2.25), "2,2");
+    }
+
+    #[test]
+    fn locale_tags_match_on_the_language() {
+        assert_eq!(Locale::from_tag("fr-FR"), Some(Locale::Fr));
+        assert_eq!(Locale::from_tag("DE_at"), Some(Locale::De));
+        assert_eq!(Locale::from_tag("es"), None);
+    }
+}
@@ -1,187 +1,2387 @@
 //! Fetch active Twitch Drop campaigns and writes them to README.md
-use anyhow::{Context, Result};
-use chrono::{DateTime, Duration, Utc};
-use reqwest;
-use serde::Deserialize;
-use std::collections::BTreeMap;
-use std::io::{BufWriter, Write};
-use tempfile::NamedTempFile;
-
-const DROPS_API_URL: &str = "https://twitch-drops-api.sunkwi.com/drops";
-const LATEST_WINDOW_DAYS: i64 = 7;
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use fuzzy_matcher::FuzzyMatcher;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use similar::TextDiff;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{ErrorKind, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::{fmt, process, thread, time};
+
+use twitch_drops_list::diff::SnapshotDiff;
+use twitch_drops_list::locale::{Locale, Translations};
+use twitch_drops_list::manifest::Manifest;
+use twitch_drops_list::markdown::{
+    self, Bullet, EmptySections, RecentGroup, RecentLabels, RecentOrder, RenderOptions, RewardSort,
+    RewardUnit, Section, WeekStart,
+};
+use twitch_drops_list::merge::{self, AliasMap};
+use twitch_drops_list::query::{DropsQuery, Excluded, ExcludedBy};
+use twitch_drops_list::retry::{Backoff, DEFAULT_MAX_RETRY_AFTER, PollJitter};
+use twitch_drops_list::state::{ActiveDrops, LastWritten, SeenDrops};
+use twitch_drops_list::summary::Summary;
+use twitch_drops_list::template::RewardTemplate;
+use twitch_drops_list::{
+    ApiDrops, ApiGame, DropsError, archive, atomic, changelog, discord, drop_id, fetch_body,
+    fetch_game_data, fingerprint, json, parse_response, preview, probe, shields, strip_emojis,
+    telemetry, truncate_names,
+};
+
 const FILE_NAME: &str = "DROPS.md";
+const JSON_FILE_NAME: &str = "DROPS.json";
+const JSONL_FILE_NAME: &str = "DROPS.jsonl";
+const DISCORD_FILE_NAME: &str = "DROPS.discord.json";
+const SHIELDS_FILE_NAME: &str = "DROPS.shields.json";
+const DEFAULT_MARKER_START: &str = "<!-- DROPS:START -->";
+const DEFAULT_MARKER_END: &str = "<!-- DROPS:END -->";
+const DEFAULT_SEEN_FILE: &str = "drops-seen.json";
+const DEFAULT_LOG_STATE_FILE: &str = "drops-log-state.json";
+const DEFAULT_KNOWN_DROPS_FILE: &str = "drops-known.json";
+const DEFAULT_LAST_WRITTEN_FILE: &str = "drops-last-written.json";
+const DEFAULT_FUZZY_THRESHOLD: i64 = 50;
+const DEFAULT_INDEX_MIN_GAMES: usize = 20;
+const DEFAULT_INDENT: usize = 2;
+const DEFAULT_LOCK_TIMEOUT_SECS: u64 = 60;
+const DEFAULT_MAX_NAME_LEN: usize = 200;
+// Exit code for --alert-if-ending-within, distinct from the 1 used for errors and by `check`
+const ALERT_EXIT_CODE: i32 = 3;
+
+// Command line options
+#[derive(Debug, Parser)]
+#[command(about = "Fetch active Twitch Drop campaigns and write them to DROPS.md")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Output format: markdown, markdown-table, json, jsonl, discord-embed or shields. Give it
+    /// as FORMAT:DEST to write it to DEST instead of --output, where DEST is - for stdout, @2
+    /// for stderr or a file. Can be repeated to write several formats from one fetch
+    #[arg(long, value_name = "FORMAT[:DEST]", value_parser = parse_format_target, default_value = "markdown")]
+    format: Vec<FormatTarget>,
+
+    /// File to write or - for stdout, defaults to DROPS.md, DROPS.json, DROPS.jsonl,
+    /// DROPS.discord.json or DROPS.shields.json depending on --format
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Pretty-print JSON output, the default unless writing to a pipe with -o -
+    #[arg(long, conflicts_with = "compact")]
+    pretty: bool,
+
+    /// Write JSON output on a single line, the default when writing to a pipe with -o -
+    #[arg(long)]
+    compact: bool,
+
+    /// Start the output with a UTF-8 byte-order mark, for consumers that need one
+    #[arg(long, conflicts_with = "inject_into")]
+    bom: bool,
+
+    /// Compress the output file, adding .gz to its name
+    #[arg(long, value_enum, default_value_t = Compress::None, conflicts_with = "inject_into")]
+    compress: Compress,
+
+    /// Line endings of the output
+    #[arg(long, value_enum, default_value_t = LineEnding::Lf)]
+    line_ending: LineEnding,
+
+    /// Copy the existing output file to <FILE>.bak before replacing it
+    #[arg(long)]
+    backup: bool,
+
+    /// Write the output without taking the lock that keeps overlapping runs from writing it at
+    /// the same time
+    #[arg(long)]
+    no_lock: bool,
+
+    /// Longest wait in SECONDS for another run to release the output, 0 to fail straight away
+    #[arg(long, value_name = "SECONDS", default_value_t = DEFAULT_LOCK_TIMEOUT_SECS, conflicts_with = "no_lock")]
+    lock_timeout: u64,
+
+    /// Leave an existing output file alone when the response has fewer than N games, which
+    /// usually means the API is having a partial outage
+    #[arg(long, value_name = "N")]
+    min_games: Option<usize>,
+
+    /// Write the output even when the response has fewer games than --min-games
+    #[arg(long, requires = "min_games")]
+    force: bool,
+
+    /// Leave the output file alone when the API says its data is not newer than the data
+    /// behind the last written list. Does nothing while the API doesn't say when it generated
+    /// the response
+    #[arg(long)]
+    write_if_newer: bool,
+
+    /// File used to remember when the data behind the last written list was generated
+    #[arg(long, value_name = "FILE", default_value = DEFAULT_LAST_WRITTEN_FILE, requires = "write_if_newer")]
+    last_written_file: PathBuf,
+
+    /// Write a JSON manifest of the files produced by the run to FILE
+    #[arg(long, value_name = "FILE")]
+    manifest: Option<PathBuf>,
+
+    /// Comma separated fields to keep in JSON output: game, dropName, startAt, endAt, imageUrl,
+    /// rewards
+    #[arg(long, value_delimiter = ',', value_name = "FIELDS")]
+    fields: Vec<String>,
+
+    /// Start the markdown with a YAML front-matter block for static site generators
+    #[arg(long, conflicts_with = "inject_into")]
+    front_matter: bool,
+
+    /// Key and value to add to the front-matter, can be repeated
+    #[arg(long, value_name = "KEY=VALUE", value_parser = parse_front_matter_key, requires = "front_matter")]
+    front_matter_key: Vec<(String, String)>,
+
+    /// Splice the list into an existing file between the markers instead of writing DROPS.md
+    #[arg(long, value_name = "FILE")]
+    inject_into: Option<PathBuf>,
+
+    /// Marker line that opens the injected region
+    #[arg(long, default_value = DEFAULT_MARKER_START, requires = "inject_into")]
+    marker_start: String,
+
+    /// Marker line that closes the injected region
+    #[arg(long, default_value = DEFAULT_MARKER_END, requires = "inject_into")]
+    marker_end: String,
+
+    /// Only write the N rewards with the highest watch time across all games
+    #[arg(long, value_name = "N")]
+    top_rewards: Option<usize>,
+
+    /// Only write the number of campaigns, games and campaigns ending within 24 hours
+    #[arg(long, conflicts_with_all = ["top_rewards", "digest", "fields"])]
+    summary_only: bool,
+
+    /// Only write a condensed digest of the current period instead of the full list
+    #[arg(long, value_enum, conflicts_with = "top_rewards")]
+    digest: Option<Digest>,
+
+    /// First day of the week for --digest week
+    #[arg(long, value_enum, default_value_t = WeekStart::Mon, requires = "digest")]
+    week_start: WeekStart,
+
+    /// Retry a failed fetch up to N times with exponential backoff
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    retries: u32,
+
+    /// Longest wait in SECONDS honoured when a rate limited response asks to retry later
+    #[arg(long, value_name = "SECONDS", default_value_t = DEFAULT_MAX_RETRY_AFTER.as_secs())]
+    max_retry_after: u64,
+
+    /// Seed for the retry and poll jitter so delays are reproducible, defaults to OS entropy
+    #[arg(long, value_name = "SEED")]
+    seed: Option<u64>,
+
+    /// Keep a copy of every API response in DIR
+    #[arg(long, value_name = "DIR")]
+    archive_dir: Option<PathBuf>,
+
+    /// If the fetch fails, render the newest response in --archive-dir instead
+    #[arg(long, requires = "archive_dir")]
+    use_cache_on_failure: bool,
+
+    /// Check the API response against the bundled JSON Schema and report every mismatch
+    #[arg(long)]
+    validate_schema: bool,
+
+    /// Render the list as of this time instead of the current time, for reproducible output
+    #[arg(long, hide = true, value_name = "TIMESTAMP", value_parser = parse_date)]
+    now: Option<DateTime<Utc>>,
+
+    /// Also write the drops that were not in the previous run's list to FILE as a JSON array
+    #[arg(long, value_name = "FILE")]
+    new_drops_json: Option<PathBuf>,
+
+    /// Append the campaigns that appeared or ended since the previous run to FILE, with the
+    /// time of the run. Nothing is appended when nothing changed
+    #[arg(long, value_name = "FILE")]
+    log_file: Option<PathBuf>,
+
+    /// File used to remember the campaigns that were active when --log-file was last written
+    #[arg(long, value_name = "FILE", default_value = DEFAULT_LOG_STATE_FILE, requires = "log_file")]
+    log_state_file: PathBuf,
+
+    /// Forget a drop remembered by --watch-only-new or --new-drops-json once it has been
+    /// missing for N runs in a row, so it is reported again if it comes back after that
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    forget_after_runs: u32,
+
+    /// File used to remember the drops in the last list written with --new-drops-json
+    #[arg(long, value_name = "FILE", default_value = DEFAULT_KNOWN_DROPS_FILE, requires = "new_drops_json")]
+    known_drops_file: PathBuf,
+
+    /// Write the duration, size and number of games, drops and rewards of the fetch as JSON
+    #[arg(long, value_name = "FILE")]
+    stats_file: Option<PathBuf>,
+
+    /// Only keep campaigns that are active or ended at most HOURS ago
+    #[arg(long, value_name = "HOURS")]
+    include_ended_within: Option<u32>,
+
+    /// TOML file of "Old Name" = "New Name" lines, merging renamed games into one entry
+    #[arg(long, value_name = "FILE")]
+    alias_map: Option<PathBuf>,
+
+    /// Only keep campaigns with a reward requiring at least MINUTES of watch time
+    #[arg(long, value_name = "MINUTES")]
+    min_minutes: Option<u16>,
+
+    /// Leave out campaigns that have no rewards, and games left without campaigns
+    #[arg(long, conflicts_with = "show_rewardless")]
+    exclude_rewardless: bool,
+
+    /// Only keep campaigns that end within HOURS
+    #[arg(long, value_name = "HOURS")]
+    ending_within: Option<u32>,
+
+    /// Only keep campaigns ending at or after DATE (YYYY-MM-DD, midnight UTC, or RFC 3339)
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    end_after: Option<DateTime<Utc>>,
+
+    /// Only keep campaigns ending before DATE (YYYY-MM-DD, midnight UTC, or RFC 3339)
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    end_before: Option<DateTime<Utc>>,
+
+    /// Order of the games
+    #[arg(long, value_enum, default_value_t = GameSort::Name)]
+    sort: GameSort,
+
+    /// Only include games whose name contains PATTERN, ignoring case
+    #[arg(long, value_name = "PATTERN")]
+    game: Option<String>,
+
+    /// Only include the games named in FILE, one per line and ignoring case. Empty lines and
+    /// lines starting with # are skipped
+    #[arg(long, value_name = "FILE")]
+    include_file: Option<PathBuf>,
+
+    /// Warn about the names in --include-file that match no game in the response
+    #[arg(long, requires = "include_file")]
+    warn_unmatched: bool,
+
+    /// Match --game fuzzily, keeping every game scoring above --fuzzy-threshold ordered by score
+    #[arg(long, requires = "game")]
+    fuzzy: bool,
+
+    /// Minimum fuzzy match score for a game to be included
+    #[arg(long, value_name = "SCORE", default_value_t = DEFAULT_FUZZY_THRESHOLD, requires = "fuzzy")]
+    fuzzy_threshold: i64,
+
+    /// Sections of the list to write, in order. Without it the latest and all drops are written,
+    /// followed by the sections turned on by their own flags
+    #[arg(long, value_delimiter = ',', value_name = "SECTIONS")]
+    sections: Vec<Section>,
+
+    /// Markdown block appended verbatim at the very end of the list, e.g. an attribution
+    #[arg(long, value_name = "TEXT")]
+    footer: Option<String>,
+
+    /// Pin a game in a favorites section before the others, matched ignoring case. Can be
+    /// repeated, the favorites are listed in the order given
+    #[arg(long, value_name = "NAME")]
+    favorite: Vec<String>,
+
+    /// Leave the favorite games out of the all drops section
+    #[arg(long, requires = "favorite")]
+    exclude_favorites_from_all: bool,
+
+    /// Add a section at the top with the N campaigns across all games that end soonest, 3 when
+    /// N is left out and none with 0
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "3")]
+    pinned_deadlines: Option<usize>,
+
+    /// Add a section with the N longest running campaigns
+    #[arg(long, value_name = "N")]
+    longest: Option<usize>,
+
+    /// Add a section with the N shortest running campaigns
+    #[arg(long, value_name = "N")]
+    shortest: Option<usize>,
+
+    /// Template for reward lines using {name}, {minutes} and {hours},
+    /// e.g. "{name} ({minutes} minutes watched)"
+    #[arg(long, value_name = "TEMPLATE")]
+    reward_format: Option<String>,
+
+    /// Order of the rewards of each drop, by default the order of the response
+    #[arg(long, value_enum)]
+    reward_sort: Option<RewardSort>,
+
+    /// Unit of the watch time of each reward under all drops. auto uses minutes below two
+    /// hours and hours from then on
+    #[arg(long, value_enum, default_value_t = RewardUnit::Minutes, conflicts_with = "reward_format")]
+    reward_unit: RewardUnit,
+
+    /// Add the exact end date and time after the relative "ends in" labels
+    #[arg(long)]
+    show_absolute_dates: bool,
+
+    /// Add how long ago each campaign started to the drops under all drops
+    #[arg(long)]
+    show_age: bool,
+
+    /// Time zones for the absolute dates, e.g. UTC,America/Los_Angeles. With several zones the
+    /// time is given in each of them
+    #[arg(long, value_name = "ZONES", value_delimiter = ',', value_parser = parse_timezone)]
+    timezone: Vec<Tz>,
+
+    /// Add an alphabetical index linking to each game when there are enough games
+    #[arg(long)]
+    index: bool,
+
+    /// Number of games --index needs to be exceeded before it adds the index
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_INDEX_MIN_GAMES, requires = "index")]
+    index_min_games: usize,
+
+    /// Order of the dates in the latest drops section
+    #[arg(long, value_enum, default_value_t = RecentOrder::Desc)]
+    recent_order: RecentOrder,
+
+    /// Group the latest drops section by start date then game, or by game then start date
+    #[arg(long, value_enum, default_value_t = RecentGroup::Date)]
+    recent_group: RecentGroup,
+
+    /// Write the start dates of the latest drops section as dates, or as Today and Yesterday
+    /// followed by dates for the older days
+    #[arg(long, value_enum, default_value_t = RecentLabels::Absolute)]
+    recent_labels: RecentLabels,
+
+    /// Label each reward as an emote, badge, in-game item or other, listed in that order
+    #[arg(long)]
+    by_reward_type: bool,
+
+    /// Message shown when no campaign started recently, `{}` is replaced with the number of days
+    #[arg(long, value_name = "TEXT")]
+    recent_empty_message: Option<String>,
+
+    /// Leave out the latest drops section entirely when no campaign started recently
+    #[arg(long, conflicts_with = "recent_empty_message")]
+    hide_empty_recent: bool,
+
+    /// Whether the latest and all drops sections are written with a placeholder or left out
+    /// when they have no campaigns
+    #[arg(long, value_enum, default_value_t = EmptySections::Always)]
+    emit_empty_sections: EmptySections,
+
+    /// Add a section with every campaign grouped by the ISO week it ends in
+    #[arg(long)]
+    group_by_end_week: bool,
+
+    /// Add a section listing the campaigns that have no rewards
+    #[arg(long)]
+    show_rewardless: bool,
+
+    /// Only list the N quickest to earn rewards of each drop, noting how many were left out
+    #[arg(long, value_name = "N")]
+    max_rewards: Option<usize>,
+
+    /// Only list the N soonest ending drops of each game under all drops, noting how many
+    /// were left out
+    #[arg(long, value_name = "N")]
+    drops_per_game_cap: Option<usize>,
+
+    /// Arrange the games of the all drops section in a table grid N games wide, filled row by
+    /// row, listing their drops without the rewards
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..), conflicts_with_all = ["index", "reward_references"])]
+    columns: u16,
+
+    /// Leave the campaigns listed under latest drops out of all drops, with a note pointing to
+    /// them. Has no effect when the latest drops section isn't written
+    #[arg(long)]
+    dedupe_across_sections: bool,
+
+    /// Add a line under all drops with the number of rewards and the hours of watch time they
+    /// take in total
+    #[arg(long)]
+    show_totals: bool,
+
+    /// Write the reward of a drop that has only one on the drop's line instead of below it
+    #[arg(long)]
+    collapse_single_reward: bool,
+
+    /// Write reward names used by several drops once as footnotes and reference them
+    #[arg(long)]
+    reward_references: bool,
+
+    /// Treat reward names differing only in case as the same reward for --reward-references.
+    /// Differences in whitespace are always ignored
+    #[arg(long, requires = "reward_references")]
+    fold_reward_case: bool,
+
+    /// Remove emoji from drop and reward names
+    #[arg(long)]
+    strip_emojis: bool,
+
+    /// Shorten drop and reward names longer than N characters, ending them with an ellipsis
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_NAME_LEN)]
+    max_name_len: usize,
+
+    /// Write names from the API verbatim instead of escaping markdown characters.
+    /// Unsafe for untrusted data: a name can then inject arbitrary markdown or HTML
+    #[arg(long)]
+    no_escape: bool,
+
+    /// Marker used for list items
+    #[arg(long, value_enum, default_value_t = Bullet::Dash)]
+    bullet: Bullet,
+
+    /// Number of spaces each nested list is indented by
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_INDENT)]
+    indent: usize,
+
+    /// Language for dates, headings and labels (en, de, fr), unknown languages fall back to en
+    #[arg(long, default_value = "en")]
+    locale: String,
+
+    /// Exit with status 3 after writing the list if any active campaign ends within HOURS
+    #[arg(long, value_name = "HOURS", conflicts_with = "watch")]
+    alert_if_ending_within: Option<u32>,
+
+    /// Only fetch and parse the response, reporting the counts, without writing any output.
+    /// The response is still archived with --archive-dir
+    #[arg(long, conflicts_with_all = ["watch", "alert_if_ending_within", "diff_against"])]
+    fetch_only: bool,
+
+    /// Print every game and campaign left out by the filters and the flag that removed it, one
+    /// JSON object per line, instead of writing the list
+    #[arg(long, conflicts_with_all = ["watch", "alert_if_ending_within", "fetch_only"])]
+    explain: bool,
+
+    /// Print the shape of the raw API response, or of the saved response in FILE, instead of
+    /// writing the list: its top level type and the keys of the first game, campaign and reward
+    /// with the types of their values. Works when the response no longer parses
+    #[arg(long, value_name = "FILE", num_args = 0..=1, conflicts_with_all = ["watch", "alert_if_ending_within", "fetch_only", "explain"])]
+    probe: Option<Option<PathBuf>>,
+
+    /// Print a markdown report of the lines the run would add, remove or modify in each output
+    /// file, grouped by game or section, instead of writing them
+    #[arg(long, conflicts_with_all = ["watch", "alert_if_ending_within", "fetch_only", "explain", "probe"])]
+    preview_diff: bool,
+
+    /// Print the campaigns added, removed, restarted or changed since the saved API response in
+    /// FILE instead of writing the list
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["watch", "alert_if_ending_within"])]
+    diff_against: Option<PathBuf>,
+
+    /// Browse the campaigns in an interactive terminal UI instead of writing the list
+    #[cfg(feature = "tui")]
+    #[arg(long, conflicts_with_all = ["watch", "alert_if_ending_within"])]
+    tui: bool,
+
+    /// Keep running, refreshing the list and printing active campaigns every SECONDS
+    #[arg(long, value_name = "SECONDS")]
+    watch: Option<u64>,
+
+    /// In watch mode, wait up to SECONDS longer than the interval, picked at random each time
+    #[arg(long, value_name = "SECONDS", default_value_t = 0, requires = "watch")]
+    poll_jitter: u64,
+
+    /// In watch mode, only print campaigns that were not active in the previous iteration
+    #[arg(long, requires = "watch")]
+    watch_only_new: bool,
+
+    /// File used to remember the campaigns already printed by --watch-only-new
+    #[arg(long, value_name = "FILE", default_value = DEFAULT_SEEN_FILE, requires = "watch_only_new")]
+    seen_file: PathBuf,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Check that the output file is up to date without writing it, printing a diff if it is not
+    Check,
+    /// Print a completion script for the given shell to stdout
+    Completions { shell: Shell },
+    /// Forget the drops in state files that aren't active now, without waiting for
+    /// --forget-after-runs runs, by default in the --seen-file and --known-drops-file
+    PruneState { files: Vec<PathBuf> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Compress {
+    None,
+    Gzip,
+}
+
+// A --format argument, with the destination given as FORMAT:DEST if any
+#[derive(Debug, Clone)]
+struct FormatTarget {
+    format: Format,
+    dest: Option<Dest>,
+}
+
+// Where a rendered format is written
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Dest {
+    Stdout,
+    Stderr,
+    File(PathBuf),
+}
+
+impl Dest {
+    fn from_path(path: PathBuf) -> Dest {
+        if path == Path::new("-") {
+            Dest::Stdout
+        } else {
+            Dest::File(path)
+        }
+    }
+}
 
-// Structs for deserialising API response
-// ApiGame contains the name of the game and a list of active drop campaigns
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct ApiGame {
-    game_display_name: String,
-    #[serde(rename = "rewards")]
-    drops: Vec<ApiDrops>,
+impl fmt::Display for Dest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Dest::Stdout => write!(f, "stdout"),
+            Dest::Stderr => write!(f, "stderr"),
+            Dest::File(path) => write!(f, "{}", path.display()),
+        }
+    }
 }
 
-// ApiDrops contains the name of the drop campaign, start and end dates, and a list of rewards
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct ApiDrops {
-    name: String,
-    start_at: DateTime<Utc>,
-    end_at: DateTime<Utc>,
-    #[serde(rename = "timeBasedDrops")]
-    rewards: Vec<ApiReward>,
+// A format to render and the resolved destination it is written to
+struct Output {
+    format: Format,
+    dest: Dest,
 }
 
-// ApiReward contains the name of the reward and the number of minutes watched required to earn it
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct ApiReward {
-    name: String,
-    #[serde(rename = "requiredMinutesWatched")]
-    minutes_required: u16,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum GameSort {
+    /// Alphabetically, or by match score with --fuzzy
+    Name,
+    /// By viewer count when the API provides one
+    Popularity,
+    /// Most recently updated first when the API says when a game's data changed
+    Updated,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Digest {
+    Week,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Markdown,
+    /// Every campaign in a single flat table, soonest ending first
+    MarkdownTable,
+    Json,
+    /// One JSON object per line and drop campaign
+    Jsonl,
+    /// Discord webhook message payloads for the latest drops
+    DiscordEmbed,
+    /// A shields.io endpoint badge with the number of active campaigns
+    Shields,
+}
+
+impl Format {
+    fn name(self) -> &'static str {
+        match self {
+            Format::Markdown => "markdown",
+            Format::MarkdownTable => "markdown-table",
+            Format::Json => "json",
+            Format::Jsonl => "jsonl",
+            Format::DiscordEmbed => "discord-embed",
+            Format::Shields => "shields",
+        }
+    }
+
+    fn default_file_name(self) -> &'static str {
+        match self {
+            Format::Markdown | Format::MarkdownTable => FILE_NAME,
+            Format::Json => JSON_FILE_NAME,
+            Format::Jsonl => JSONL_FILE_NAME,
+            Format::DiscordEmbed => DISCORD_FILE_NAME,
+            Format::Shields => SHIELDS_FILE_NAME,
+        }
+    }
 }
 
 fn main() -> Result<()> {
-    let mut games = fetch_game_data()?;
-    games.sort_by_key(|g| g.game_display_name.to_lowercase());
+    let cli = Cli::parse();
+    let _telemetry = telemetry::init();
 
-    let mut temp_file = NamedTempFile::new().context("failed to create temporary file")?;
+    if let Some(Command::Completions { shell }) = cli.command {
+        let mut command = Cli::command();
+        let name = command.get_name().to_string();
+        clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+        return Ok(());
+    }
 
-    {
-        let mut writer = BufWriter::new(&mut temp_file);
-        writeln!(writer, "# Twitch Drops Campaigns\n")?;
+    if let Some(Command::PruneState { files }) = &cli.command {
+        return prune_state(&cli, files);
+    }
+
+    if cli.fetch_only {
+        return fetch_only(&cli);
+    }
 
-        if games.is_empty() {
-            writeln!(writer, "No active drops campaigns found.")?;
-            return Ok(());
+    if let Some(path) = &cli.probe {
+        return probe(path.as_deref());
+    }
+
+    validate_flags(&cli)?;
+    let options = render_options(&cli)?;
+
+    if let Some(Command::Check) = cli.command {
+        return check(&cli, &options);
+    }
+
+    if let Some(path) = &cli.diff_against {
+        return diff_against(&cli, path);
+    }
+
+    if cli.preview_diff {
+        return preview_diff(&cli, &options);
+    }
+
+    if cli.explain {
+        return explain(&cli);
+    }
+
+    #[cfg(feature = "tui")]
+    if cli.tui {
+        let games = fetch_sorted_games(&cli)?.games;
+        return Ok(twitch_drops_list::tui::run(games, &options)?);
+    }
+
+    match cli.watch {
+        Some(interval) => watch(&cli, &options, interval),
+        None => {
+            let games = run(&cli, &options, &mut None)?;
+            if let Some(hours) = cli.alert_if_ending_within
+                && let Some(code) = alert_ending_within(&games, hours, now(&cli), options.text)
+            {
+                process::exit(code);
+            }
+            Ok(())
         }
+    }
+}
 
-        let now = Utc::now();
-        write_latest_drops(&games, now, &mut writer)?;
-        write_all_games(&games, now, &mut writer)?;
+// Reject the combinations of flags that clap can't express
+fn validate_flags(cli: &Cli) -> Result<()> {
+    json::validate_fields(&cli.fields)?;
+    if !cli.fields.is_empty() && !has_format(cli, &[Format::Json, Format::Jsonl]) {
+        bail!("--fields only applies to --format json and jsonl");
+    }
+    if cli.top_rewards.is_some() && !has_format(cli, &[Format::Markdown]) {
+        bail!("--top-rewards only supports --format markdown");
+    }
+    if cli.digest.is_some() && !has_format(cli, &[Format::Markdown]) {
+        bail!("--digest only supports --format markdown");
     }
+    if let (Some(after), Some(before)) = (cli.end_after, cli.end_before)
+        && after > before
+    {
+        bail!("--end-after must not be later than --end-before");
+    }
+    if cli.summary_only
+        && let Some(target) = cli.format.iter().find(|target| {
+            matches!(
+                target.format,
+                Format::DiscordEmbed | Format::MarkdownTable | Format::Shields
+            )
+        })
+    {
+        bail!(
+            "--summary-only does not support --format {}",
+            target.format.name()
+        );
+    }
+    if cli.footer.is_some() && !has_format(cli, &[Format::Markdown, Format::MarkdownTable]) {
+        bail!("--footer only supports --format markdown and markdown-table");
+    }
+    if cli.compress != Compress::None
+        && (cli.output.as_deref() == Some(Path::new("-"))
+            || cli
+                .format
+                .iter()
+                .any(|target| matches!(target.dest, Some(Dest::Stdout | Dest::Stderr))))
+    {
+        bail!("--compress needs an output file, not stdout or stderr");
+    }
+    if cli.front_matter && !has_format(cli, &[Format::Markdown]) {
+        bail!("--front-matter only supports --format markdown");
+    }
+    if cli.inject_into.is_some()
+        && (cli.format.len() > 1 || cli.format.iter().any(|target| target.dest.is_some()))
+    {
+        bail!("--inject-into only supports a single --format without a destination");
+    }
+    // Two formats written to the same place would interleave or overwrite each other
+    let outputs = outputs(cli);
+    for (i, output) in outputs.iter().enumerate() {
+        if let Some(other) = outputs[..i].iter().find(|other| other.dest == output.dest) {
+            bail!(
+                "--format {} and --format {} both write to {}",
+                other.format.name(),
+                output.format.name(),
+                output.dest
+            );
+        }
+    }
+    Ok(())
+}
 
-    temp_file
-        .persist(FILE_NAME)
-        .context("failed to persist file")?;
+// The settings of the markdown writers from the command line
+fn render_options(cli: &Cli) -> Result<RenderOptions> {
+    let locale = Locale::from_tag(&cli.locale).unwrap_or_else(|| {
+        eprintln!("unknown locale `{}`, falling back to en", cli.locale);
+        Locale::En
+    });
+    Ok(RenderOptions {
+        now: now(cli),
+        text: locale.translations(),
+        pinned_deadlines: cli.pinned_deadlines,
+        longest: cli.longest,
+        shortest: cli.shortest,
+        reward_format: cli
+            .reward_format
+            .as_deref()
+            .map(RewardTemplate::parse)
+            .transpose()?,
+        reward_unit: cli.reward_unit,
+        reward_sort: cli.reward_sort,
+        show_absolute_dates: cli.show_absolute_dates,
+        timezones: cli.timezone.clone(),
+        index: cli.index,
+        index_min_games: cli.index_min_games,
+        recent_order: cli.recent_order,
+        recent_group: cli.recent_group,
+        recent_labels: cli.recent_labels,
+        by_reward_type: cli.by_reward_type,
+        recent_empty_message: cli.recent_empty_message.clone(),
+        hide_empty_recent: cli.hide_empty_recent,
+        empty_sections: cli.emit_empty_sections,
+        sections: sections(cli)?,
+        max_rewards: cli.max_rewards,
+        drops_per_game_cap: cli.drops_per_game_cap,
+        columns: cli.columns.into(),
+        favorites: cli.favorite.clone(),
+        favorites_in_all: !cli.exclude_favorites_from_all,
+        reward_references: cli.reward_references,
+        fold_reward_case: cli.fold_reward_case,
+        dedupe_across_sections: cli.dedupe_across_sections,
+        show_totals: cli.show_totals,
+        collapse_single_reward: cli.collapse_single_reward,
+        show_age: cli.show_age,
+        bullet: cli.bullet,
+        indent: cli.indent,
+        escape: !cli.no_escape,
+    })
+}
+
+fn backoff(cli: &Cli) -> Backoff {
+    Backoff::new(cli.seed).max_retry_after(time::Duration::from_secs(cli.max_retry_after))
+}
+
+// Compact the state files, skipping the ones that don't exist. The campaigns are fetched with
+// the same filters as a normal run, since those decide which drops the files remember
+fn prune_state(cli: &Cli, files: &[PathBuf]) -> Result<()> {
+    let defaults = [cli.seen_file.clone(), cli.known_drops_file.clone()];
+    let files = if files.is_empty() {
+        &defaults[..]
+    } else {
+        files
+    };
+    let current = drop_ids(&fetch_sorted_games(cli)?.games);
+    for path in files.iter().filter(|path| path.exists()) {
+        let mut seen = SeenDrops::load(path)?;
+        let forgotten = seen.forget_inactive(&current);
+        seen.save(path)?;
+        eprintln!("{}: forgot {} drops", path.display(), forgotten);
+    }
+    Ok(())
+}
+
+// Fetch and parse the response as a pre-flight check, failing if either fails
+fn fetch_only(cli: &Cli) -> Result<()> {
+    let fetched = fetch_game_data(cli.retries, cli.validate_schema, &mut backoff(cli))?;
+    if let Some(dir) = &cli.archive_dir {
+        archive::save(dir, &fetched.raw, now(cli))?;
+    }
+    if let Some(path) = &cli.stats_file {
+        fetched.stats.write(path)?;
+    }
+    Ok(())
+}
+
+// Print the shape of the response, fetched once unless a saved one is given
+fn probe(path: Option<&Path>) -> Result<()> {
+    let raw = match path {
+        Some(path) => fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?,
+        None => fetch_body()?,
+    };
+    let mut stdout = std::io::stdout().lock();
+    probe::write_report(&raw, &mut stdout)?;
+    Ok(())
+}
+
+// Compare the current campaigns with a saved response and print what changed
+fn diff_against(cli: &Cli, path: &Path) -> Result<()> {
+    let raw =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let (snapshot, _) =
+        parse_response(&raw).with_context(|| format!("failed to parse {}", path.display()))?;
+    let games = fetch_sorted_games(cli)?.games;
 
+    let mut stdout = std::io::stdout().lock();
+    SnapshotDiff::between(&snapshot, &games).write_report(&mut stdout)?;
     Ok(())
 }
 
-// Write the list of drop campaigns that started recently, organised by date
-fn write_latest_drops(
+// Print the games and campaigns the filters left out with the flag responsible
+fn explain(cli: &Cli) -> Result<()> {
+    for excluded in fetch_sorted_games(cli)?.excluded {
+        let line = serde_json::json!({
+            "game": excluded.game,
+            "drop": excluded.drop,
+            "filter": excluded.filter,
+        });
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+// Print the active campaigns ending within the given number of hours, returning the
+// ALERT_EXIT_CODE to exit with if there are any
+fn alert_ending_within(
     games: &[ApiGame],
+    hours: u32,
     now: DateTime<Utc>,
-    writer: &mut impl Write,
-) -> Result<()> {
-    let updates_from = now - Duration::days(LATEST_WINDOW_DAYS);
+    text: &Translations,
+) -> Option<i32> {
+    let ending = ending_within(games, hours, now);
+    for (game, drop) in &ending {
+        eprintln!(
+            "alert: {}: {} ({})",
+            game.game_display_name,
+            drop.name,
+            markdown::ends_in_days(drop.end_at, now, text)
+        );
+    }
+    if ending.is_empty() {
+        return None;
+    }
+    eprintln!("{} campaigns end within {} hours", ending.len(), hours);
+    Some(ALERT_EXIT_CODE)
+}
+
+// The campaigns that haven't ended yet but will within the given number of hours
+fn ending_within(games: &[ApiGame], hours: u32, now: DateTime<Utc>) -> Vec<(&ApiGame, &ApiDrops)> {
+    let deadline = now + chrono::Duration::hours(hours.into());
+    games
+        .iter()
+        .flat_map(|game| game.drops.iter().map(move |drop| (game, drop)))
+        .filter(|(_, drop)| drop.end_at > now && drop.end_at <= deadline)
+        .collect()
+}
+
+// Refresh the list forever, printing campaign notifications after every update
+fn watch(cli: &Cli, options: &RenderOptions, interval: u64) -> Result<()> {
+    let interval = time::Duration::from_secs(interval);
+    let mut jitter = PollJitter::new(time::Duration::from_secs(cli.poll_jitter), cli.seed);
+    let mut last_fingerprint = None;
+    loop {
+        let notified = run(cli, options, &mut last_fingerprint)
+            .and_then(|games| notify(cli, &games, options.text));
+        if let Err(err) = notified {
+            eprintln!("update failed: {:#}", err);
+        }
+        thread::sleep(jitter.interval(interval));
+    }
+}
+
+// Print a line for each active campaign, or only the unseen ones with --watch-only-new
+fn notify(cli: &Cli, games: &[ApiGame], text: &Translations) -> Result<()> {
+    let now = now(cli);
+    let current = drop_ids(games);
+
+    let new = if cli.watch_only_new {
+        let mut seen = SeenDrops::load(&cli.seen_file)?;
+        let new = seen.update(current, cli.forget_after_runs);
+        seen.save(&cli.seen_file)?;
+        new
+    } else {
+        current
+    };
 
-    let mut latest_updates: BTreeMap<chrono::NaiveDate, BTreeMap<&str, Vec<&ApiDrops>>> =
-        BTreeMap::new();
     for game in games {
-        for drop in game.drops.iter().filter(|d| d.start_at > updates_from) {
-            latest_updates
-                .entry(drop.start_at.date_naive())
-                .or_default()
-                .entry(&game.game_display_name)
-                .or_default()
-                .push(drop);
+        for drop in game
+            .drops
+            .iter()
+            .filter(|d| new.contains(&drop_id(game, d)))
+        {
+            println!(
+                "{}: {} ({})",
+                game.game_display_name,
+                drop.name,
+                markdown::ends_in_days(drop.end_at, now, text)
+            );
         }
     }
+    Ok(())
+}
+
+// Render the list in memory and compare it with the output file, exiting with status 1 if they differ
+fn check(cli: &Cli, options: &RenderOptions) -> Result<()> {
+    if cli.compress != Compress::None {
+        bail!("check does not support --compress");
+    }
+    let mut up_to_date = true;
+    for Comparison {
+        path,
+        on_disk,
+        expected,
+    } in compare_outputs(cli, options)?
+    {
+        if expected == on_disk {
+            eprintln!("{} is up to date", path.display());
+            continue;
+        }
 
-    writeln!(writer, "## Latest drops\n")?;
+        let name = path.display().to_string();
+        eprint!(
+            "{}",
+            TextDiff::from_lines(&on_disk, &expected)
+                .unified_diff()
+                .header(&name, &name)
+        );
+        up_to_date = false;
+    }
+    if !up_to_date {
+        process::exit(1);
+    }
+    Ok(())
+}
 
-    if latest_updates.is_empty() {
-        writeln!(
-            writer,
-            "No drop campaigns started in the last {} days.\n",
-            LATEST_WINDOW_DAYS
+// Print a markdown report of the lines the run would change in each output file, grouped by
+// the game or section they are in, without writing anything
+fn preview_diff(cli: &Cli, options: &RenderOptions) -> Result<()> {
+    if cli.compress != Compress::None {
+        bail!("--preview-diff does not support --compress");
+    }
+    let mut stdout = std::io::stdout().lock();
+    for comparison in compare_outputs(cli, options)? {
+        preview::write_report(
+            &comparison.path.display().to_string(),
+            &comparison.on_disk,
+            &comparison.expected,
+            &mut stdout,
         )?;
-        return Ok(());
     }
+    Ok(())
+}
+
+// An output file as it is on disk and as the run would write it
+struct Comparison {
+    path: PathBuf,
+    on_disk: String,
+    expected: String,
+}
+
+// Render every output written to a file and read the file it would replace. Formats written
+// to stdout or stderr have no file to compare with
+fn compare_outputs(cli: &Cli, options: &RenderOptions) -> Result<Vec<Comparison>> {
+    let games = fetch_sorted_games(cli)?.games;
+    let options = RenderOptions {
+        now: now(cli),
+        ..options.clone()
+    };
+
+    let mut comparisons = Vec::new();
+    for output in outputs(cli) {
+        let Dest::File(path) = output.dest.clone() else {
+            continue;
+        };
+        let rendered = render_to_string(cli, &output, &games, &options)?;
+        let on_disk = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to read {}", path.display()));
+            }
+        };
+        let expected = match &cli.inject_into {
+            Some(_) => {
+                splice_between_markers(&on_disk, &rendered, &cli.marker_start, &cli.marker_end)
+                    .with_context(|| format!("failed to check {}", path.display()))?
+            }
+            None => rendered,
+        };
+        comparisons.push(Comparison {
+            path,
+            on_disk,
+            expected,
+        });
+    }
+    Ok(comparisons)
+}
+
+// The campaigns to write, with what is known about the response they came from
+struct Campaigns {
+    games: Vec<ApiGame>,
+    // When the API generated the response, if it says
+    generated_at: Option<DateTime<Utc>>,
+    // Number of games in the response before any filtering
+    response_games: usize,
+    // What the filters left out, for --explain
+    excluded: Vec<Exclusion>,
+}
+
+// A game, or a drop when given, left out by the filter of a flag
+struct Exclusion {
+    game: String,
+    drop: Option<String>,
+    filter: &'static str,
+}
+
+impl From<Excluded> for Exclusion {
+    fn from(excluded: Excluded) -> Exclusion {
+        let filter = match excluded.by {
+            ExcludedBy::GameContains => "--game",
+            ExcludedBy::GamesNamed => "--include-file",
+            ExcludedBy::MinMinutes => "--min-minutes",
+            ExcludedBy::EndingWithin => "--ending-within",
+            ExcludedBy::EndedWithin => "--include-ended-within",
+            ExcludedBy::EndAfter => "--end-after",
+            ExcludedBy::EndBefore => "--end-before",
+            ExcludedBy::WithRewards => "--exclude-rewardless",
+            ExcludedBy::NoDropsLeft => "no campaigns left",
+        };
+        Exclusion {
+            game: excluded.game,
+            drop: excluded.drop,
+            filter,
+        }
+    }
+}
 
-    for (date, games_for_date) in latest_updates.iter().rev() {
-        writeln!(writer, "{}", date.format("%Y-%m-%d"))?;
-        for (game, drops) in games_for_date {
-            writeln!(writer, "- {}", escape_markdown(game))?;
-            for drop in drops {
-                writeln!(
-                    writer,
-                    "  - {} ({})",
-                    escape_markdown(&drop.name),
-                    ends_in_days(drop.end_at, now)
-                )?;
+// Fetch the active campaigns, sorted by game name and filtered by --game
+fn fetch_sorted_games(cli: &Cli) -> Result<Campaigns> {
+    let (games, generated_at) =
+        match fetch_game_data(cli.retries, cli.validate_schema, &mut backoff(cli)) {
+            Ok(fetched) => {
+                if let Some(dir) = &cli.archive_dir
+                    && let Err(err) = archive::save(dir, &fetched.raw, now(cli))
+                {
+                    eprintln!("failed to archive response: {:#}", err);
+                }
+                if let Some(path) = &cli.stats_file
+                    && let Err(err) = fetched.stats.write(path)
+                {
+                    eprintln!("failed to write fetch stats: {:#}", err);
+                }
+                (fetched.games, fetched.generated_at)
+            }
+            Err(err) => match cli
+                .archive_dir
+                .as_deref()
+                .filter(|_| cli.use_cache_on_failure)
+            {
+                Some(dir) => {
+                    eprintln!("fetch failed: {:#}", err);
+                    load_archived_games(dir, now(cli))
+                        .context("no usable cached response to fall back to")?
+                }
+                None => return Err(err.into()),
+            },
+        };
+    sort_and_filter(cli, games, generated_at)
+}
+
+// Apply the aliases, clean-ups, filters and sort order given on the command line to the games
+// of a response
+fn sort_and_filter(
+    cli: &Cli,
+    mut games: Vec<ApiGame>,
+    generated_at: Option<DateTime<Utc>>,
+) -> Result<Campaigns> {
+    let response_games = games.len();
+    if let Some(path) = &cli.alias_map {
+        AliasMap::load(path)?.apply(&mut games);
+    }
+    let mut games = merge::merge_games(games);
+    if cli.strip_emojis {
+        strip_emojis(&mut games);
+    }
+    truncate_names(&mut games, cli.max_name_len);
+    games.sort_by_key(|g| g.game_display_name.to_lowercase());
+
+    let included = cli
+        .include_file
+        .as_deref()
+        .map(load_include_file)
+        .transpose()?;
+    if let Some(names) = &included
+        && cli.warn_unmatched
+    {
+        for name in names {
+            let lower = name.to_lowercase();
+            if !games
+                .iter()
+                .any(|game| game.game_display_name.to_lowercase() == lower)
+            {
+                eprintln!("warning: no game named `{}` in the response", name);
             }
         }
-        writeln!(writer)?;
+    }
+
+    let mut query = DropsQuery::new(games).now(now(cli));
+    if let Some(names) = &included {
+        query = query.games_named(names);
+    }
+    if let Some(pattern) = cli.game.as_deref().filter(|_| !cli.fuzzy) {
+        query = query.game_contains(pattern);
+    }
+    if let Some(minutes) = cli.min_minutes {
+        query = query.min_minutes(minutes);
+    }
+    if cli.exclude_rewardless {
+        query = query.with_rewards();
+    }
+    if let Some(hours) = cli.ending_within {
+        query = query.ending_within(chrono::Duration::hours(hours.into()));
+    }
+    if let Some(hours) = cli.include_ended_within {
+        query = query.ended_within(chrono::Duration::hours(hours.into()));
+    }
+    if let Some(after) = cli.end_after {
+        query = query.end_after(after);
+    }
+    if let Some(before) = cli.end_before {
+        query = query.end_before(before);
+    }
+    let (games, excluded) = query.collect_explained();
+    let mut excluded: Vec<Exclusion> = excluded.into_iter().map(Exclusion::from).collect();
+
+    let mut games = match &cli.game {
+        Some(pattern) if cli.fuzzy => {
+            let names: Vec<String> = games.iter().map(|g| g.game_display_name.clone()).collect();
+            let games = fuzzy_filter_games(games, pattern, cli.fuzzy_threshold);
+            excluded.extend(
+                names
+                    .into_iter()
+                    .filter(|name| !games.iter().any(|g| &g.game_display_name == name))
+                    .map(|game| Exclusion {
+                        game,
+                        drop: None,
+                        filter: "--fuzzy",
+                    }),
+            );
+            games
+        }
+        _ => games,
+    };
+    match cli.sort {
+        // Most watched first, games without a viewer count keep their order at the end
+        GameSort::Popularity => {
+            games.sort_by_key(|g| (g.viewer_count.is_none(), std::cmp::Reverse(g.viewer_count)))
+        }
+        // Likewise for the games without an update time
+        GameSort::Updated => {
+            games.sort_by_key(|g| (g.updated_at.is_none(), std::cmp::Reverse(g.updated_at)))
+        }
+        GameSort::Name => {}
+    }
+    Ok(Campaigns {
+        games,
+        generated_at,
+        response_games,
+        excluded,
+    })
+}
+
+// Load the newest archived response, warning that it may be out of date
+fn load_archived_games(
+    dir: &Path,
+    now: DateTime<Utc>,
+) -> Result<(Vec<ApiGame>, Option<DateTime<Utc>>)> {
+    let archived = archive::latest(dir)?
+        .ok_or_else(|| anyhow!("no archived responses in {}", dir.display()))?;
+    let response = parse_response(&archived.raw)
+        .with_context(|| format!("failed to parse {}", archived.path.display()))?;
+
+    let age = now - archived.fetched_at;
+    let age = if age.num_hours() < 1 {
+        format!("{} minutes", age.num_minutes())
+    } else if age.num_hours() < 48 {
+        format!("{} hours", age.num_hours())
+    } else {
+        format!("{} days", age.num_days())
+    };
+    eprintln!(
+        "warning: using cached response {} fetched {} ago, the data may be stale",
+        archived.path.display(),
+        age
+    );
+    Ok(response)
+}
+
+// Read the game names of --include-file
+fn load_include_file(path: &Path) -> Result<Vec<String>> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+// Keep the games that fuzzily match the pattern above the threshold, best match first
+fn fuzzy_filter_games(games: Vec<ApiGame>, pattern: &str, threshold: i64) -> Vec<ApiGame> {
+    let matcher = SkimMatcherV2::default().ignore_case();
+    let mut scored: Vec<(i64, ApiGame)> = games
+        .into_iter()
+        .filter_map(|game| {
+            matcher
+                .fuzzy_match(&game.game_display_name, pattern)
+                .filter(|score| *score >= threshold)
+                .map(|score| (score, game))
+        })
+        .collect();
+    // The sort is stable, so equal scores keep their alphabetical order
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    scored.into_iter().map(|(_, game)| game).collect()
+}
+
+// Whether any of the --format arguments is one of the given formats
+fn has_format(cli: &Cli, formats: &[Format]) -> bool {
+    cli.format
+        .iter()
+        .any(|target| formats.contains(&target.format))
+}
+
+// The formats to render and where each is written: the destination given with FORMAT:DEST,
+// otherwise the injection target, --output or the format's default file. Files get .gz added
+// when they are compressed
+fn outputs(cli: &Cli) -> Vec<Output> {
+    cli.format
+        .iter()
+        .map(|target| {
+            let dest = match &target.dest {
+                Some(Dest::File(path)) => Dest::File(compressed_path(cli, path.clone())),
+                Some(dest) => dest.clone(),
+                None => match &cli.inject_into {
+                    Some(target) => Dest::File(target.clone()),
+                    None => match cli.output.clone() {
+                        Some(path) if path == Path::new("-") => Dest::Stdout,
+                        path => Dest::from_path(compressed_path(
+                            cli,
+                            path.unwrap_or_else(|| {
+                                PathBuf::from(target.format.default_file_name())
+                            }),
+                        )),
+                    },
+                },
+            };
+            Output {
+                format: target.format,
+                dest,
+            }
+        })
+        .collect()
+}
+
+fn compressed_path(cli: &Cli, path: PathBuf) -> PathBuf {
+    match cli.compress {
+        Compress::None => path,
+        Compress::Gzip => {
+            let mut compressed = path.into_os_string();
+            compressed.push(".gz");
+            PathBuf::from(compressed)
+        }
+    }
+}
+
+// Whether to pretty-print JSON: --pretty and --compact win, otherwise JSON written to stdout
+// or stderr is compact unless it is a terminal, and JSON written to a file is pretty
+fn pretty_json(cli: &Cli, dest: &Dest) -> bool {
+    if cli.pretty || cli.compact {
+        return cli.pretty;
+    }
+    match dest {
+        Dest::Stdout => std::io::stdout().is_terminal(),
+        Dest::Stderr => std::io::stderr().is_terminal(),
+        Dest::File(_) => true,
+    }
+}
+
+// Fetch, render and write the list once, returning the fetched games
+fn run(
+    cli: &Cli,
+    options: &RenderOptions,
+    last_fingerprint: &mut Option<String>,
+) -> Result<Vec<ApiGame>> {
+    let campaigns = fetch_sorted_games(cli)?;
+    let options = RenderOptions {
+        now: now(cli),
+        ..options.clone()
+    };
+    write_if_changed(cli, &campaigns, &options, last_fingerprint)?;
+    Ok(campaigns.games)
+}
+
+// Render and write the campaigns unless they have the same fingerprint as the last written
+// list, which is updated after every successful write
+fn write_if_changed(
+    cli: &Cli,
+    campaigns: &Campaigns,
+    options: &RenderOptions,
+    last_fingerprint: &mut Option<String>,
+) -> Result<()> {
+    let current = fingerprint(
+        &campaigns.games,
+        &markdown::time_labels(&campaigns.games, options),
+    );
+    if last_fingerprint.as_ref() == Some(&current) {
+        eprintln!("unchanged");
+        return Ok(());
+    }
+    if write_outputs(cli, campaigns, options)? {
+        *last_fingerprint = Some(current);
     }
     Ok(())
 }
 
-// Write the full list of currently active drop campaigns by game
-fn write_all_games(games: &[ApiGame], now: DateTime<Utc>, writer: &mut impl Write) -> Result<()> {
-    writeln!(writer, "## All drops\n")?;
-    for game in games {
-        writeln!(writer, "{}", escape_markdown(&game.game_display_name))?;
-        for drop in &game.drops {
-            let end = ends_in_days(drop.end_at, now);
-            writeln!(writer, "- {} ({})", escape_markdown(&drop.name), end)?;
-            for reward in &drop.rewards {
-                writeln!(
-                    writer,
-                    "  - {} ({} minutes watched)",
-                    escape_markdown(&reward.name),
-                    reward.minutes_required
-                )?;
+// Render the campaigns in every format asked for and write them out, along with the state
+// files that follow the written list. Returns false when a guard kept them from being written
+fn write_outputs(cli: &Cli, campaigns: &Campaigns, options: &RenderOptions) -> Result<bool> {
+    write_outputs_with(cli, campaigns, options, |output| {
+        render_to_string(cli, output, &campaigns.games, options)
+    })
+}
+
+// write_outputs with the step that renders each output passed in
+fn write_outputs_with(
+    cli: &Cli,
+    campaigns: &Campaigns,
+    options: &RenderOptions,
+    mut render: impl FnMut(&Output) -> Result<String>,
+) -> Result<bool> {
+    let games = &campaigns.games;
+    let outputs = outputs(cli);
+    let files: Vec<&Path> = outputs
+        .iter()
+        .filter_map(|output| match &output.dest {
+            Dest::File(path) => Some(path.as_path()),
+            _ => None,
+        })
+        .collect();
+
+    // Held until the run returns, so overlapping runs render and write one after the other
+    let _locks = if cli.no_lock {
+        Vec::new()
+    } else {
+        files
+            .iter()
+            .map(|path| atomic::lock(path, time::Duration::from_secs(cli.lock_timeout)))
+            .collect::<twitch_drops_list::Result<Vec<_>>>()?
+    };
+
+    // Render everything up front so a failure part way through never touches the output files
+    let rendered = outputs
+        .iter()
+        .map(&mut render)
+        .collect::<Result<Vec<_>>>()?;
+
+    // The checks below are about replacing files, so they keep every output from being
+    // written, stdout and stderr included
+    let existing: Vec<String> = files
+        .iter()
+        .filter(|path| path.exists())
+        .map(|path| path.display().to_string())
+        .collect();
+    if let Some(min) = cli.min_games
+        && campaigns.response_games < min
+        && !cli.force
+        && !existing.is_empty()
+    {
+        eprintln!(
+            "warning: the response only has {} games, fewer than --min-games {}, not replacing {} (use --force to write anyway)",
+            campaigns.response_games,
+            min,
+            existing.join(", ")
+        );
+        return Ok(false);
+    }
+    if cli.write_if_newer
+        && !files.is_empty()
+        && let Some(generated_at) = campaigns.generated_at
+        && let Some(last) = LastWritten::load(&cli.last_written_file)?
+        && generated_at <= last.generated_at
+    {
+        let names: Vec<String> = files
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        eprintln!(
+            "response generated at {} is not newer than the one behind {} ({}), not writing",
+            generated_at.to_rfc3339(),
+            names.join(", "),
+            last.generated_at.to_rfc3339()
+        );
+        return Ok(false);
+    }
+
+    // The manifest is informational, so failing to write it only warns
+    let mut manifest = Manifest::default();
+    for (output, rendered) in outputs.iter().zip(&rendered) {
+        let path = match &output.dest {
+            Dest::Stdout => {
+                print!("{}", rendered);
+                continue;
+            }
+            Dest::Stderr => {
+                eprint!("{}", rendered);
+                continue;
             }
+            Dest::File(path) => path,
+        };
+        let previous = cli.manifest.as_ref().and_then(|_| fs::read(path).ok());
+
+        if cli.backup {
+            atomic::backup(path)?;
         }
-        writeln!(writer)?;
+        if let Some(target) = &cli.inject_into {
+            inject_into_file(target, rendered, &cli.marker_start, &cli.marker_end)?;
+        } else if cli.compress == Compress::Gzip {
+            atomic::write_file_gzip(path, rendered.as_bytes())?;
+        } else {
+            atomic::write_file(path, rendered.as_bytes())?;
+        }
+        if cli.manifest.is_some()
+            && let Err(err) = manifest.record(path, output.format.name(), previous.as_deref())
+        {
+            eprintln!("failed to write manifest: {:#}", err);
+        }
+    }
+    if cli.write_if_newer
+        && !files.is_empty()
+        && let Some(generated_at) = campaigns.generated_at
+    {
+        LastWritten { generated_at }.save(&cli.last_written_file)?;
+    }
+    write_new_drops(cli, games)?;
+    append_log(cli, games, options.now)?;
+
+    if let Some(path) = &cli.manifest
+        && !files.is_empty()
+        && let Err(err) = manifest.write(path)
+    {
+        eprintln!("failed to write manifest: {:#}", err);
+    }
+
+    Ok(true)
+}
+
+// Write the drops missing from --known-drops-file to --new-drops-json, then remember the
+// current drops. The state is only saved once the file is written so no new drop is lost
+fn write_new_drops(cli: &Cli, games: &[ApiGame]) -> Result<()> {
+    let Some(path) = &cli.new_drops_json else {
+        return Ok(());
+    };
+    let mut known = SeenDrops::load(&cli.known_drops_file)?;
+    let new = known.update(drop_ids(games), cli.forget_after_runs);
+    let rendered = json::render_new(games, &new, !cli.compact)?;
+    atomic::write_file(path, rendered.as_bytes())?;
+    known.save(&cli.known_drops_file)?;
+    Ok(())
+}
+
+// Append what changed since the previous run to --log-file, then remember the active
+// campaigns. As with --new-drops-json the state is only saved once the log is written
+fn append_log(cli: &Cli, games: &[ApiGame], now: DateTime<Utc>) -> Result<()> {
+    let Some(path) = &cli.log_file else {
+        return Ok(());
+    };
+    let previous = ActiveDrops::load(&cli.log_state_file)?;
+    let current = changelog::active_drops(games, now);
+    if let Some(entry) = changelog::entry(&previous, &current, now) {
+        changelog::append(path, &entry)?;
     }
+    current.save(&cli.log_state_file)?;
     Ok(())
 }
 
-// Fetches the list of currently active Twitch Drop campaigns, listed by game name
-fn fetch_game_data() -> Result<Vec<ApiGame>> {
-    eprintln!("fetching open drop campaigns...");
+// The stable IDs of every drop campaign
+fn drop_ids(games: &[ApiGame]) -> BTreeSet<String> {
+    games
+        .iter()
+        .flat_map(|game| game.drops.iter().map(move |drop| drop_id(game, drop)))
+        .collect()
+}
 
-    let game_data = reqwest::blocking::get(DROPS_API_URL)
-        .context("failed to fetch from api")?
-        .json::<Vec<ApiGame>>()
-        .context("failed to parse json response")?;
+// Render the output selected on the command line into memory, with the line endings and
+// byte-order mark asked for
+fn render_to_string(
+    cli: &Cli,
+    output: &Output,
+    games: &[ApiGame],
+    options: &RenderOptions,
+) -> Result<String> {
+    let mut rendered = telemetry::span("render", |span| {
+        span.set("games", games.len());
+        let rendered = render_content(cli, output, games, options)?;
+        span.set("bytes", rendered.len());
+        Ok::<_, anyhow::Error>(rendered)
+    })?;
+    if cli.line_ending == LineEnding::Crlf {
+        rendered = rendered.replace('\n', "\r\n");
+    }
+    if cli.bom {
+        rendered.insert(0, '\u{feff}');
+    }
+    Ok(rendered)
+}
 
-    Ok(game_data)
+fn render_content(
+    cli: &Cli,
+    output: &Output,
+    games: &[ApiGame],
+    options: &RenderOptions,
+) -> Result<String> {
+    let pretty = pretty_json(cli, &output.dest);
+    let rendered = match output.format {
+        Format::Json if cli.summary_only => {
+            json::render_summary(&Summary::of(games, options.now), pretty)
+        }
+        Format::Jsonl if cli.summary_only => {
+            json::render_summary(&Summary::of(games, options.now), false)
+        }
+        Format::Json => json::render(games, &cli.fields, pretty),
+        Format::Jsonl => json::render_lines(games, &cli.fields, options.now),
+        Format::DiscordEmbed => discord::render(games, options.now, options.text, pretty),
+        Format::Shields => shields::render(games, options.now, pretty),
+        Format::Markdown => {
+            let mut rendered = Vec::new();
+            render(cli, games, options, &mut rendered)?;
+            return String::from_utf8(rendered).context("rendered list is not valid utf-8");
+        }
+        Format::MarkdownTable => {
+            let mut rendered = Vec::new();
+            markdown::write_table(games, options, &mut rendered)?;
+            write_footer(cli, &mut rendered)?;
+            return String::from_utf8(rendered).context("rendered list is not valid utf-8");
+        }
+    };
+    Ok(rendered?)
 }
 
-// Escape markdown special characters
-fn escape_markdown(text: &str) -> String {
-    let mut escaped = String::with_capacity(text.len());
-    for c in text.chars() {
-        match c {
-            '\\' | '`' | '*' | '_' | '{' | '}' | '[' | ']' | '(' | ')' | '#' | '+' | '-' | '.'
-            | '!' | '|' | '<' | '>' | '~' => {
-                escaped.push('\\');
-                escaped.push(c);
-            }
-            _ => escaped.push(c),
+// Render the markdown output selected on the command line
+fn render(
+    cli: &Cli,
+    games: &[ApiGame],
+    options: &RenderOptions,
+    writer: &mut impl Write,
+) -> twitch_drops_list::Result<()> {
+    if cli.front_matter {
+        markdown::write_front_matter(&cli.front_matter_key, options.now, writer)?;
+    }
+    if cli.summary_only {
+        markdown::write_summary(&Summary::of(games, options.now), options, writer)?;
+    } else if let Some(Digest::Week) = cli.digest {
+        markdown::write_weekly_digest(games, cli.week_start, options, writer)?;
+    } else if let Some(count) = cli.top_rewards {
+        markdown::write_top_rewards(games, count, options, writer)?;
+    } else {
+        markdown::write_document(games, options, writer)?;
+    }
+    write_footer(cli, writer)
+}
+
+// Append the --footer block verbatim, on its own lines
+fn write_footer(cli: &Cli, writer: &mut impl Write) -> twitch_drops_list::Result<()> {
+    if let Some(footer) = &cli.footer {
+        writeln!(writer, "{}", footer.trim_end_matches('\n')).map_err(DropsError::Write)?;
+    }
+    Ok(())
+}
+
+// The sections to write, from --sections or from the flags that add sections
+fn sections(cli: &Cli) -> Result<Vec<Section>> {
+    if cli.sections.is_empty() {
+        let mut sections = vec![Section::Recent, Section::All];
+        if !cli.favorite.is_empty() {
+            sections.insert(0, Section::Favorites);
+        }
+        if cli.pinned_deadlines.is_some_and(|n| n > 0) {
+            sections.insert(0, Section::Deadlines);
+        }
+        if cli.group_by_end_week {
+            sections.push(Section::EndWeek);
+        }
+        if cli.show_rewardless {
+            sections.push(Section::Rewardless);
+        }
+        if cli.longest.is_some() {
+            sections.push(Section::Longest);
+        }
+        if cli.shortest.is_some() {
+            sections.push(Section::Shortest);
         }
+        return Ok(sections);
     }
-    escaped
+    if cli.sections.contains(&Section::Favorites) && cli.favorite.is_empty() {
+        bail!("--sections favorites needs --favorite NAME for the games to pin");
+    }
+    if cli.sections.contains(&Section::Deadlines) && cli.pinned_deadlines.is_none_or(|n| n == 0) {
+        bail!("--sections deadlines needs --pinned-deadlines N for the number of campaigns");
+    }
+    if cli.sections.contains(&Section::Longest) && cli.longest.is_none() {
+        bail!("--sections longest needs --longest N for the number of campaigns");
+    }
+    if cli.sections.contains(&Section::Shortest) && cli.shortest.is_none() {
+        bail!("--sections shortest needs --shortest N for the number of campaigns");
+    }
+    Ok(cli.sections.clone())
 }
 
-// Calculate days until end date and format as a human-readable string
-fn ends_in_days(end: DateTime<Utc>, now: DateTime<Utc>) -> String {
-    let days = end.signed_duration_since(now).num_days() as i16;
-    if days < 0 {
-        return "already ended".to_string();
+// The time the list is rendered for, the current time unless overridden with --now
+fn now(cli: &Cli) -> DateTime<Utc> {
+    cli.now.unwrap_or_else(Utc::now)
+}
+
+// Parse a date given as YYYY-MM-DD, meaning midnight UTC, or as a full RFC 3339 timestamp
+fn parse_date(arg: &str) -> Result<DateTime<Utc>> {
+    if let Ok(date) = NaiveDate::parse_from_str(arg, "%Y-%m-%d") {
+        return Ok(date.and_time(NaiveTime::MIN).and_utc());
     }
-    return format!("ends {}", format_days_from_now(days));
+    let time = DateTime::parse_from_rfc3339(arg).with_context(|| {
+        format!(
+            "expected YYYY-MM-DD or an RFC 3339 timestamp, got `{}`",
+            arg
+        )
+    })?;
+    Ok(time.with_timezone(&Utc))
+}
+
+// Parse an IANA time zone name such as Europe/Berlin
+fn parse_timezone(arg: &str) -> Result<Tz> {
+    arg.trim().parse().map_err(|_| {
+        anyhow!(
+            "unknown time zone `{}`, expected an IANA name such as Europe/Berlin",
+            arg
+        )
+    })
 }
 
-// Format a number of days from now into a human-readable string - for future dates only
-fn format_days_from_now(days: i16) -> String {
-    match days {
-        0 => "today".into(),
-        1 => "tomorrow".into(),
-        _ => format!("in {} days", days),
+// Parse a --format argument given as FORMAT or FORMAT:DEST
+fn parse_format_target(arg: &str) -> Result<FormatTarget> {
+    let (name, dest) = match arg.split_once(':') {
+        Some((name, dest)) => (name, Some(dest)),
+        None => (arg, None),
+    };
+    let format = Format::from_str(name, true).map_err(|_| {
+        let names: Vec<&str> = Format::value_variants().iter().map(|f| f.name()).collect();
+        anyhow!(
+            "unknown format `{}`, expected one of: {}",
+            name,
+            names.join(", ")
+        )
+    })?;
+    let dest = match dest {
+        None => None,
+        Some("") => bail!("missing destination after `{}:`", name),
+        Some("@2") => Some(Dest::Stderr),
+        Some(path) => Some(Dest::from_path(PathBuf::from(path))),
+    };
+    Ok(FormatTarget { format, dest })
+}
+
+// Parse a --front-matter-key argument of the form key=value
+fn parse_front_matter_key(arg: &str) -> Result<(String, String)> {
+    let (key, value) = arg
+        .split_once('=')
+        .ok_or_else(|| anyhow!("expected KEY=VALUE, got `{}`", arg))?;
+    let key = key.trim();
+    if key.is_empty()
+        || !key
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+    {
+        bail!(
+            "front-matter key `{}` may only contain letters, digits, `_` and `-`",
+            key
+        );
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+// Replace everything between the start and end markers of an existing file with the rendered list
+fn inject_into_file(
+    path: &Path,
+    rendered: &str,
+    marker_start: &str,
+    marker_end: &str,
+) -> Result<()> {
+    let original =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let spliced = splice_between_markers(&original, rendered, marker_start, marker_end)
+        .with_context(|| format!("failed to inject drops list into {}", path.display()))?;
+    atomic::write_file(path, spliced.as_bytes())?;
+    Ok(())
+}
+
+// Splice the rendered list between the first start marker and the end marker that follows it
+fn splice_between_markers(
+    original: &str,
+    rendered: &str,
+    marker_start: &str,
+    marker_end: &str,
+) -> Result<String> {
+    let start = original.find(marker_start).ok_or_else(|| {
+        anyhow!(
+            "start marker `{}` not found, add it where the list should begin",
+            marker_start
+        )
+    })?;
+    let body_start = start + marker_start.len();
+    let body_end = original[body_start..]
+        .find(marker_end)
+        .map(|offset| body_start + offset)
+        .ok_or_else(|| {
+            anyhow!(
+                "end marker `{}` not found after `{}`, add it where the list should end",
+                marker_end,
+                marker_start
+            )
+        })?;
+
+    Ok(format!(
+        "{}\n{}\n{}",
+        &original[..body_start],
+        rendered.trim_end(),
+        &original[body_end..]
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use twitch_drops_list::ApiReward;
+
+    fn cli(args: &[&str]) -> Cli {
+        let base = ["twitch-drops-list", "--now", "2026-10-15T00:00:00Z"];
+        Cli::try_parse_from(base.iter().chain(args)).unwrap()
+    }
+
+    fn campaigns() -> Campaigns {
+        let (games, generated_at) =
+            parse_response(include_str!("../tests/fixtures/drops-valid.json")).unwrap();
+        Campaigns {
+            response_games: games.len(),
+            games,
+            generated_at,
+            excluded: Vec::new(),
+        }
+    }
+
+    // A game with a campaign from 2026-10-10 to 2026-10-24 and one reward
+    fn game(name: &str) -> ApiGame {
+        ApiGame {
+            game_display_name: name.to_string(),
+            viewer_count: None,
+            updated_at: None,
+            drops: vec![ApiDrops {
+                name: format!("{} Drops", name),
+                start_at: "2026-10-10T17:00:00Z".parse().unwrap(),
+                end_at: "2026-10-24T17:00:00Z".parse().unwrap(),
+                image_url: None,
+                detail_url: None,
+                rewards: vec![ApiReward {
+                    name: "Badge".to_string(),
+                    minutes_required: 60,
+                }],
+            }],
+        }
+    }
+
+    fn names(games: &[ApiGame]) -> Vec<&str> {
+        games.iter().map(|g| g.game_display_name.as_str()).collect()
+    }
+
+    fn format_to(format: &str, path: &Path) -> String {
+        format!("{}:{}", format, path.display())
+    }
+
+    #[test]
+    fn a_failed_write_stops_before_the_outputs_after_it() {
+        let dir = tempfile::tempdir().unwrap();
+        // A directory can't be replaced by the rendered file
+        let blocked = dir.path().join("DROPS.json");
+        fs::create_dir(&blocked).unwrap();
+        let markdown = dir.path().join("DROPS.md");
+        fs::write(&markdown, "old").unwrap();
+        let cli = cli(&[
+            "--format",
+            &format_to("json", &blocked),
+            "--format",
+            &format_to("markdown", &markdown),
+        ]);
+        let options = render_options(&cli).unwrap();
+        assert!(write_outputs(&cli, &campaigns(), &options).is_err());
+        assert!(blocked.is_dir());
+        assert_eq!(fs::read_to_string(&markdown).unwrap(), "old");
+    }
+
+    #[test]
+    fn every_output_is_written_once_all_of_them_rendered() {
+        let dir = tempfile::tempdir().unwrap();
+        let markdown = dir.path().join("DROPS.md");
+        let json = dir.path().join("DROPS.json");
+        let cli = cli(&[
+            "--format",
+            &format_to("markdown", &markdown),
+            "--format",
+            &format_to("json", &json),
+        ]);
+        let options = render_options(&cli).unwrap();
+        assert!(write_outputs(&cli, &campaigns(), &options).unwrap());
+        assert!(fs::read_to_string(&markdown).unwrap().contains("Hoodie"));
+        assert!(fs::read_to_string(&json).unwrap().contains("Hoodie"));
+    }
+
+    #[test]
+    fn fuzzy_matching_keeps_close_names_best_first() {
+        let games = vec![game("Rust"), game("Counter-Strike 2"), game("Trust Fall")];
+        let kept = fuzzy_filter_games(games, "rust", DEFAULT_FUZZY_THRESHOLD);
+        assert_eq!(names(&kept), ["Rust", "Trust Fall"]);
+    }
+
+    #[test]
+    fn fuzzy_game_filter_explains_what_it_left_out() {
+        let cli = cli(&["--game", "counterstrike", "--fuzzy"]);
+        let games = vec![game("Rust"), game("Counter-Strike 2")];
+        let campaigns = sort_and_filter(&cli, games, None).unwrap();
+        assert_eq!(names(&campaigns.games), ["Counter-Strike 2"]);
+        assert_eq!(campaigns.excluded.len(), 1);
+        assert_eq!(campaigns.excluded[0].game, "Rust");
+        assert_eq!(campaigns.excluded[0].filter, "--fuzzy");
+    }
+
+    #[test]
+    fn alerts_cover_campaigns_ending_within_the_hours() {
+        let now = cli(&[]).now.unwrap();
+        let mut ended = game("Ended");
+        ended.drops[0].end_at = "2026-10-14T00:00:00Z".parse().unwrap();
+        let mut ending_now = game("Ending now");
+        ending_now.drops[0].end_at = now;
+        let games = vec![ended, ending_now, game("Ending")];
+        // The campaign ends exactly 233 hours after now
+        assert!(ending_within(&games, 232, now).is_empty());
+        let ending = ending_within(&games, 233, now);
+        assert_eq!(ending.len(), 1);
+        assert_eq!(ending[0].0.game_display_name, "Ending");
+    }
+
+    #[test]
+    fn alerts_exit_with_their_own_code_only_when_a_campaign_ends_soon() {
+        let now = cli(&[]).now.unwrap();
+        let text = Locale::En.translations();
+        let mut soon = game("Soon");
+        soon.drops[0].end_at = now + chrono::Duration::hours(2);
+        assert_eq!(alert_ending_within(&[game("Later")], 6, now, text), None);
+        assert_eq!(
+            alert_ending_within(&[game("Later"), soon], 6, now, text),
+            Some(ALERT_EXIT_CODE)
+        );
+    }
+
+    #[test]
+    fn popularity_puts_the_most_watched_first_and_unknown_last() {
+        let mut games = vec![game("Unknown"), game("Few"), game("Many")];
+        games[1].viewer_count = Some(10);
+        games[2].viewer_count = Some(5_000);
+        let campaigns = sort_and_filter(&cli(&["--sort", "popularity"]), games, None).unwrap();
+        assert_eq!(names(&campaigns.games), ["Many", "Few", "Unknown"]);
+    }
+
+    #[test]
+    fn the_end_window_must_not_be_reversed() {
+        let reversed = cli(&["--end-after", "2026-11-01", "--end-before", "2026-10-01"]);
+        assert!(validate_flags(&reversed).is_err());
+        let window = cli(&["--end-after", "2026-10-01", "--end-before", "2026-11-01"]);
+        assert!(validate_flags(&window).is_ok());
+        let campaigns = sort_and_filter(&window, vec![game("Inside")], None).unwrap();
+        assert_eq!(names(&campaigns.games), ["Inside"]);
+        let early = cli(&["--end-before", "2026-10-24"]);
+        assert!(
+            sort_and_filter(&early, vec![game("Late")], None)
+                .unwrap()
+                .games
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn renamed_games_from_the_alias_map_merge_with_the_new_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let map = dir.path().join("aliases.toml");
+        fs::write(&map, "\"Old Name\" = \"New Name\"\n").unwrap();
+        let mut old = game("Old Name");
+        old.drops[0].name = "Earlier Drops".to_string();
+        let games = vec![old, game("New Name"), game("Other")];
+        let cli = cli(&["--alias-map", map.to_str().unwrap()]);
+        let campaigns = sort_and_filter(&cli, games, None).unwrap();
+        assert_eq!(names(&campaigns.games), ["New Name", "Other"]);
+        assert_eq!(campaigns.games[0].drops.len(), 2);
+
+        fs::write(&map, "not toml").unwrap();
+        assert!(sort_and_filter(&cli, vec![game("Old Name")], None).is_err());
+    }
+
+    #[test]
+    fn files_get_the_chosen_line_endings_and_byte_order_mark() {
+        let dir = tempfile::tempdir().unwrap();
+        let markdown = dir.path().join("DROPS.md");
+        let target = format_to("markdown", &markdown);
+        let lf = cli(&["--format", &target]);
+        write_outputs(&lf, &campaigns(), &render_options(&lf).unwrap()).unwrap();
+        let written = fs::read(&markdown).unwrap();
+        assert!(written.starts_with(b"# "));
+        assert!(!written.contains(&b'\r'));
+
+        let crlf = cli(&["--format", &target, "--bom", "--line-ending", "crlf"]);
+        write_outputs(&crlf, &campaigns(), &render_options(&crlf).unwrap()).unwrap();
+        let written = fs::read_to_string(&markdown).unwrap();
+        assert!(written.starts_with("\u{feff}# "));
+        assert_eq!(
+            written.matches('\n').count(),
+            written.matches("\r\n").count()
+        );
+    }
+
+    #[test]
+    fn an_older_response_does_not_replace_a_newer_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let markdown = dir.path().join("DROPS.md");
+        let last_written = dir.path().join("last-written.json");
+        let cli = cli(&[
+            "--format",
+            &format_to("markdown", &markdown),
+            "--write-if-newer",
+            "--last-written-file",
+            last_written.to_str().unwrap(),
+        ]);
+        let options = render_options(&cli).unwrap();
+        let generated = |time: &str, name: &str| Campaigns {
+            games: vec![game(name)],
+            generated_at: Some(time.parse().unwrap()),
+            response_games: 1,
+            excluded: Vec::new(),
+        };
+
+        let newer = generated("2026-10-14T12:00:00Z", "Newer");
+        assert!(write_outputs(&cli, &newer, &options).unwrap());
+        let older = generated("2026-10-14T11:00:00Z", "Older");
+        assert!(!write_outputs(&cli, &older, &options).unwrap());
+        assert!(fs::read_to_string(&markdown).unwrap().contains("Newer"));
+
+        // Without a generation time there is nothing to compare, so the guard doesn't apply
+        let unknown = Campaigns {
+            generated_at: None,
+            ..generated("2026-10-14T11:00:00Z", "Unknown")
+        };
+        assert!(write_outputs(&cli, &unknown, &options).unwrap());
+        assert!(fs::read_to_string(&markdown).unwrap().contains("Unknown"));
+    }
+
+    #[test]
+    fn every_time_zone_is_validated() {
+        let zones = cli(&["--timezone", "UTC,America/Los_Angeles"]).timezone;
+        assert_eq!(zones, [Tz::UTC, Tz::America__Los_Angeles]);
+        let args = ["twitch-drops-list", "--timezone", "UTC,Mars/Olympus"];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn bash_completions_cover_the_flags() {
+        Cli::command().debug_assert();
+        let command = cli(&["completions", "bash"]).command;
+        let Some(Command::Completions { shell }) = command else {
+            panic!("expected the completions subcommand");
+        };
+        let mut script = Vec::new();
+        clap_complete::generate(shell, &mut Cli::command(), "twitch-drops-list", &mut script);
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("--alert-if-ending-within"));
+        assert!(script.contains("prune-state"));
+    }
+
+    #[test]
+    fn sections_are_parsed_in_order_and_checked() {
+        assert_eq!(
+            sections(&cli(&[])).unwrap(),
+            [Section::Recent, Section::All]
+        );
+        let reordered = cli(&["--sections", "all,recent,rewardless"]);
+        assert_eq!(
+            sections(&reordered).unwrap(),
+            [Section::All, Section::Recent, Section::Rewardless]
+        );
+        assert!(sections(&cli(&["--sections", "favorites"])).is_err());
+        let args = ["twitch-drops-list", "--sections", "all,nope"];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn a_short_response_does_not_replace_an_existing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let markdown = dir.path().join("DROPS.md");
+        let target = format_to("markdown", &markdown);
+        let short = Campaigns {
+            games: vec![game("Only")],
+            generated_at: None,
+            response_games: 1,
+            excluded: Vec::new(),
+        };
+        let guarded = cli(&["--format", &target, "--min-games", "5"]);
+        let options = render_options(&guarded).unwrap();
+        // Nothing to lose yet, so the first list is written
+        assert!(write_outputs(&guarded, &short, &options).unwrap());
+        fs::write(&markdown, "old").unwrap();
+        assert!(!write_outputs(&guarded, &short, &options).unwrap());
+        assert_eq!(fs::read_to_string(&markdown).unwrap(), "old");
+
+        let forced = cli(&["--format", &target, "--min-games", "5", "--force"]);
+        assert!(write_outputs(&forced, &short, &options).unwrap());
+        assert!(fs::read_to_string(&markdown).unwrap().contains("Only"));
+    }
+
+    #[test]
+    fn the_footer_comes_last_and_verbatim() {
+        let footer = "Data from [drops](https://example.com/drops_api)\n*Not affiliated*";
+        let with_footer = cli(&["--footer", footer]);
+        let options = render_options(&with_footer).unwrap();
+        let output = &outputs(&with_footer)[0];
+        let rendered =
+            render_to_string(&with_footer, output, &campaigns().games, &options).unwrap();
+        assert!(rendered.ends_with(&format!("{}\n", footer)));
+        assert!(rendered.find("Hoodie").unwrap() < rendered.find(footer).unwrap());
+
+        let without = cli(&[]);
+        let rendered = render_to_string(&without, output, &campaigns().games, &options).unwrap();
+        assert!(!rendered.contains("affiliated"));
+        assert!(validate_flags(&cli(&["--footer", footer, "--format", "json"])).is_err());
+    }
+
+    #[test]
+    fn only_drops_missing_from_the_last_run_are_new() {
+        let dir = tempfile::tempdir().unwrap();
+        let new_drops = dir.path().join("new.json");
+        let known = dir.path().join("known.json");
+        let cli = cli(&[
+            "--new-drops-json",
+            new_drops.to_str().unwrap(),
+            "--known-drops-file",
+            known.to_str().unwrap(),
+        ]);
+        let new_games = |games: &[ApiGame]| {
+            write_new_drops(&cli, games).unwrap();
+            let written: serde_json::Value =
+                serde_json::from_str(&fs::read_to_string(&new_drops).unwrap()).unwrap();
+            let records = written.as_array().unwrap().clone();
+            for record in &records {
+                assert_eq!(record["id"].as_str().unwrap().len(), 16);
+            }
+            records
+                .iter()
+                .map(|record| record["game"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(new_games(&[game("First")]), ["First"]);
+        assert_eq!(new_games(&[game("First"), game("Second")]), ["Second"]);
+        assert!(new_games(&[game("First"), game("Second")]).is_empty());
+    }
+
+    #[test]
+    fn names_are_truncated_before_they_are_escaped() {
+        let mut long = game("Game");
+        long.drops[0].rewards[0].name = "*".repeat(1000);
+        let truncate = cli(&["--max-name-len", "5"]);
+        let games = sort_and_filter(&truncate, vec![long], None).unwrap().games;
+        let options = render_options(&truncate).unwrap();
+        let rendered =
+            render_to_string(&truncate, &outputs(&truncate)[0], &games, &options).unwrap();
+        assert!(rendered.contains(r"\*\*\*\*…"));
+        assert!(!rendered.contains(r"\*\*\*\*\*"));
+    }
+
+    #[test]
+    fn the_log_only_grows_when_campaigns_appear_or_end() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("drops.log");
+        let state = dir.path().join("log-state.json");
+        let cli = cli(&[
+            "--log-file",
+            log.to_str().unwrap(),
+            "--log-state-file",
+            state.to_str().unwrap(),
+        ]);
+        let now = cli.now.unwrap();
+        append_log(&cli, &[game("First")], now).unwrap();
+        let first = fs::read_to_string(&log).unwrap();
+        assert_eq!(
+            first,
+            "== 2026-10-15 00:00 UTC: 1 appeared, 0 ended\n\
+             + First: First Drops (2026-10-10 17:00 to 2026-10-24 17:00)\n\n"
+        );
+        append_log(&cli, &[game("First")], now).unwrap();
+        assert_eq!(fs::read_to_string(&log).unwrap(), first);
+
+        // By the 25th the first campaign has ended
+        let later = "2026-10-25T00:00:00Z".parse().unwrap();
+        let mut second = game("Second");
+        second.drops[0].end_at = "2026-10-30T17:00:00Z".parse().unwrap();
+        append_log(&cli, &[game("First"), second], later).unwrap();
+        let written = fs::read_to_string(&log).unwrap();
+        assert_eq!(
+            written.strip_prefix(&first).unwrap(),
+            "== 2026-10-25 00:00 UTC: 1 appeared, 1 ended\n\
+             + Second: Second Drops (2026-10-10 17:00 to 2026-10-30 17:00)\n\
+             - First: First Drops (2026-10-10 17:00 to 2026-10-24 17:00)\n\n"
+        );
+    }
+
+    #[test]
+    fn explain_names_the_flag_that_left_a_drop_out() {
+        let mut short = game("Short");
+        short.drops[0].rewards[0].minutes_required = 15;
+        let mut mixed = game("Mixed");
+        let mut quick = game("Mixed").drops.remove(0);
+        quick.name = "Quick".to_string();
+        quick.rewards[0].minutes_required = 15;
+        mixed.drops.push(quick);
+        let cli = cli(&["--min-minutes", "30"]);
+        let campaigns = sort_and_filter(&cli, vec![short, mixed], None).unwrap();
+        assert_eq!(names(&campaigns.games), ["Mixed"]);
+        let excluded: Vec<_> = campaigns
+            .excluded
+            .iter()
+            .map(|e| (e.game.as_str(), e.drop.as_deref(), e.filter))
+            .collect();
+        assert_eq!(
+            excluded,
+            [
+                ("Mixed", Some("Quick"), "--min-minutes"),
+                ("Short", Some("Short Drops"), "--min-minutes"),
+                ("Short", None, "no campaigns left"),
+            ]
+        );
+    }
+
+    #[test]
+    fn compressed_outputs_decompress_to_the_plain_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = dir.path().join("DROPS.json");
+        let plain = cli(&["--format", &format_to("json", &json)]);
+        let options = render_options(&plain).unwrap();
+        write_outputs(&plain, &campaigns(), &options).unwrap();
+        let gzip = cli(&["--format", &format_to("json", &json), "--compress", "gzip"]);
+        write_outputs(&gzip, &campaigns(), &options).unwrap();
+
+        let mut decompressed = Vec::new();
+        let file = fs::File::open(dir.path().join("DROPS.json.gz")).unwrap();
+        flate2::read::GzDecoder::new(file)
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, fs::read(&json).unwrap());
+        assert!(validate_flags(&cli(&["--compress", "gzip", "--output", "-"])).is_err());
+    }
+
+    #[test]
+    fn only_games_in_the_include_file_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let include = dir.path().join("games.txt");
+        fs::write(
+            &include,
+            "# tracked games\nvalorant\n\n  Apex Legends  \nMissing Game\n",
+        )
+        .unwrap();
+        let games = vec![game("Valorant"), game("Rust"), game("Apex Legends")];
+        let cli = cli(&[
+            "--include-file",
+            include.to_str().unwrap(),
+            "--sort",
+            "name",
+        ]);
+        let campaigns = sort_and_filter(&cli, games, None).unwrap();
+        assert_eq!(names(&campaigns.games), ["Apex Legends", "Valorant"]);
+        assert_eq!(campaigns.excluded[0].game, "Rust");
+        assert_eq!(campaigns.excluded[0].filter, "--include-file");
+    }
+
+    #[test]
+    fn each_format_goes_to_its_own_destination() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = dir.path().join("drops.json");
+        let split = cli(&[
+            "--format",
+            "markdown:-",
+            "--format",
+            &format_to("json", &json),
+        ]);
+        let dests: Vec<Dest> = outputs(&split).into_iter().map(|o| o.dest).collect();
+        assert_eq!(dests, [Dest::Stdout, Dest::File(json.clone())]);
+        assert!(validate_flags(&split).is_ok());
+        write_outputs(&split, &campaigns(), &render_options(&split).unwrap()).unwrap();
+        assert!(fs::read_to_string(&json).unwrap().starts_with('['));
+
+        let stderr = parse_format_target("discord-embed:@2").unwrap();
+        assert_eq!(stderr.dest, Some(Dest::Stderr));
+        assert!(parse_format_target("json:").is_err());
+        assert!(parse_format_target("yaml:-").is_err());
+        let both = cli(&["--format", "markdown:-", "--format", "json:-"]);
+        assert!(validate_flags(&both).is_err());
+    }
+
+    #[test]
+    fn zero_pinned_deadlines_turns_the_block_off() {
+        let pinned = sections(&cli(&["--pinned-deadlines", "3"])).unwrap();
+        assert_eq!(pinned, [Section::Deadlines, Section::Recent, Section::All]);
+        let off = sections(&cli(&["--pinned-deadlines", "0"])).unwrap();
+        assert_eq!(off, [Section::Recent, Section::All]);
+    }
+
+    #[test]
+    fn a_locked_output_is_only_written_with_no_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let markdown = dir.path().join("DROPS.md");
+        let target = format_to("markdown", &markdown);
+        let _held = atomic::lock(&markdown, time::Duration::ZERO).unwrap();
+        let waiting = cli(&["--format", &target, "--lock-timeout", "0"]);
+        let options = render_options(&waiting).unwrap();
+        assert!(write_outputs(&waiting, &campaigns(), &options).is_err());
+        assert!(!markdown.exists());
+        let unlocked = cli(&["--format", &target, "--no-lock"]);
+        assert!(write_outputs(&unlocked, &campaigns(), &options).unwrap());
+    }
+
+    #[test]
+    fn the_same_campaigns_are_only_rendered_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = dir.path().join("DROPS.json");
+        let cli = cli(&["--format", &format_to("json", &json)]);
+        let options = render_options(&cli).unwrap();
+        let mut last_fingerprint = None;
+        write_if_changed(&cli, &campaigns(), &options, &mut last_fingerprint).unwrap();
+        assert!(json.exists());
+        let written = last_fingerprint.clone();
+        assert!(written.is_some());
+
+        fs::remove_file(&json).unwrap();
+        write_if_changed(&cli, &campaigns(), &options, &mut last_fingerprint).unwrap();
+        assert!(!json.exists());
+        assert_eq!(last_fingerprint, written);
+    }
+
+    // A writer that takes `left` bytes and then fails like a full disk
+    struct FailAfter<'a> {
+        left: usize,
+        written: &'a mut Vec<u8>,
+    }
+
+    impl Write for FailAfter<'_> {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if buf.len() > self.left {
+                return Err(std::io::Error::other("no space left on device"));
+            }
+            self.left -= buf.len();
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn a_render_failing_part_way_leaves_the_list_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let markdown = dir.path().join("DROPS.md");
+        fs::write(&markdown, "old\r\nlist\n").unwrap();
+        let cli = cli(&["--format", &format_to("markdown", &markdown)]);
+        let options = render_options(&cli).unwrap();
+        let campaigns = campaigns();
+        let mut partial = Vec::new();
+        let written = write_outputs_with(&cli, &campaigns, &options, |_| {
+            let mut writer = FailAfter {
+                left: 64,
+                written: &mut partial,
+            };
+            render(&cli, &campaigns.games, &options, &mut writer)?;
+            Ok(String::from_utf8(partial.clone())?)
+        });
+        assert!(written.is_err());
+        assert!(partial.starts_with(b"# Twitch Drops Campaigns"));
+        assert_eq!(fs::read(&markdown).unwrap(), b"old\r\nlist\n");
+    }
+
+    const START: &str = "<!-- DROPS:START -->";
+    const END: &str = "<!-- DROPS:END -->";
+
+    #[test]
+    fn splicing_replaces_only_the_text_between_the_markers() {
+        let original = "# Readme\r\n\nIntro  \n<!-- DROPS:START -->\nold list\n<!-- DROPS:END -->\n\nOutro\t\n";
+        let spliced = splice_between_markers(original, "new list\n\n", START, END).unwrap();
+        assert_eq!(
+            spliced,
+            "# Readme\r\n\nIntro  \n<!-- DROPS:START -->\nnew list\n<!-- DROPS:END -->\n\nOutro\t\n"
+        );
+        let again = splice_between_markers(&spliced, "new list\n\n", START, END).unwrap();
+        assert_eq!(again, spliced);
+    }
+
+    #[test]
+    fn splicing_needs_both_markers_in_order() {
+        let err = splice_between_markers("no markers\n", "list", START, END).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "start marker `<!-- DROPS:START -->` not found, add it where the list should begin"
+        );
+        let backwards = "<!-- DROPS:END -->\n<!-- DROPS:START -->\n";
+        let err = splice_between_markers(backwards, "list", START, END).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "end marker `<!-- DROPS:END -->` not found after `<!-- DROPS:START -->`, add it where the list should end"
+        );
+    }
+
+    #[test]
+    fn front_matter_is_quoted_yaml_left_alone_by_the_escaper() {
+        let cli = cli(&[
+            "--front-matter",
+            "--front-matter-key",
+            "title=*A* [b] | c",
+            "--front-matter-key",
+            "layout=page",
+        ]);
+        let options = render_options(&cli).unwrap();
+        let mut rendered = Vec::new();
+        render(&cli, &campaigns().games, &options, &mut rendered).unwrap();
+        let rendered = String::from_utf8(rendered).unwrap();
+        assert!(rendered.starts_with(
+            "---\ntitle: \"*A* [b] | c\"\nlayout: \"page\"\nupdated: 2026-10-15T00:00:00Z\n---\n\n# "
+        ));
+        assert!(!rendered.contains("\\*A\\*"));
+        assert!(!rendered.contains("\\[b\\]"));
     }
 }
@@ -0,0 +1,46 @@
+//! Machine-readable list of the files written by a run
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{DropsError, Result};
+
+#[derive(Debug, Default, Serialize)]
+pub struct Manifest {
+    outputs: Vec<ManifestEntry>,
+}
+
+// A single output file, its format, size and whether this run changed its contents
+#[derive(Debug, Serialize)]
+pub struct ManifestEntry {
+    path: PathBuf,
+    format: &'static str,
+    bytes: u64,
+    changed: bool,
+}
+
+impl Manifest {
+    // Record a file that has just been written, given its contents before the write
+    pub fn record(
+        &mut self,
+        path: &Path,
+        format: &'static str,
+        previous: Option<&[u8]>,
+    ) -> Result<()> {
+        let current = fs::read(path).map_err(DropsError::io("read", path))?;
+        self.outputs.push(ManifestEntry {
+            path: path.to_path_buf(),
+            format,
+            bytes: current.len() as u64,
+            changed: previous != Some(current.as_slice()),
+        });
+        Ok(())
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let mut contents =
+            serde_json::to_string_pretty(self).map_err(DropsError::serialise("manifest"))?;
+        contents.push('\n');
+        fs::write(path, contents).map_err(DropsError::io("write", path))
+    }
+}
@@ -0,0 +1,2372 @@
+//! Markdown rendering of the drop campaigns
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use lru::LruCache;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::num::NonZeroUsize;
+use std::rc::Rc;
+
+use crate::api::{ApiDrops, ApiGame, ApiReward};
+use crate::error::{DropsError, Result};
+use crate::locale::Translations;
+use crate::reward::{canonical_reward_name, classify_reward};
+use crate::summary::Summary;
+use crate::template::{RewardTemplate, format_hours};
+
+pub const LATEST_WINDOW_DAYS: i64 = 7;
+
+// Order of the date groups in the latest drops section
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum RecentOrder {
+    // Newest dates first
+    Desc,
+    // Oldest dates first
+    Asc,
+}
+
+// Outer grouping of the latest drops section
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum RecentGroup {
+    // By start date, then by game within each date
+    Date,
+    // By game, then by start date within each game
+    Game,
+}
+
+// How the start dates of the latest drops section are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum RecentLabels {
+    // As dates in the locale's format
+    Absolute,
+    // Today and Yesterday, then dates for the older days
+    Relative,
+}
+
+// Whether a section with nothing to list is still written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum EmptySections {
+    // With its heading and a placeholder message
+    Always,
+    // Not at all
+    Never,
+}
+
+// Unit of the watch time of the rewards under all drops
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum RewardUnit {
+    Minutes,
+    // Hours with one decimal place
+    Hours,
+    // Minutes below two hours, whole or fractional hours from then on
+    Auto,
+}
+
+// Order of the rewards of a drop
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum RewardSort {
+    // Quickest to earn first
+    Minutes,
+    // Alphabetically, ignoring case
+    Name,
+    // As listed in the response
+    Api,
+}
+
+// Sections of the full document, listed with --sections in the order they are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Section {
+    // The campaigns ending soonest across all games
+    Deadlines,
+    // The games given with --favorite, in that order
+    Favorites,
+    // Campaigns that started recently
+    Recent,
+    // Every campaign by game
+    All,
+    // Every campaign by the week it ends in
+    EndWeek,
+    // Campaigns without rewards
+    Rewardless,
+    // The longest running campaigns
+    Longest,
+    // The shortest running campaigns
+    Shortest,
+}
+
+// List item marker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Bullet {
+    Dash,
+    Star,
+    Plus,
+}
+
+impl Bullet {
+    fn marker(self) -> char {
+        match self {
+            Bullet::Dash => '-',
+            Bullet::Star => '*',
+            Bullet::Plus => '+',
+        }
+    }
+}
+
+// First day of the week for the weekly digest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum WeekStart {
+    Mon,
+    Sun,
+}
+
+// Number of games listed in the weekly digest
+const DIGEST_TOP_GAMES: usize = 5;
+
+// Settings shared by the markdown writers
+#[derive(Clone)]
+pub struct RenderOptions {
+    pub now: DateTime<Utc>,
+    pub text: &'static Translations,
+    pub pinned_deadlines: Option<usize>,
+    pub longest: Option<usize>,
+    pub shortest: Option<usize>,
+    pub reward_format: Option<RewardTemplate>,
+    pub reward_unit: RewardUnit,
+    // Without it rewards keep the response order, or the order --max-rewards picked them in
+    pub reward_sort: Option<RewardSort>,
+    pub show_absolute_dates: bool,
+    // Zones the absolute dates are given in, UTC when empty
+    pub timezones: Vec<Tz>,
+    pub index: bool,
+    pub index_min_games: usize,
+    pub recent_order: RecentOrder,
+    pub recent_group: RecentGroup,
+    pub recent_labels: RecentLabels,
+    pub by_reward_type: bool,
+    pub recent_empty_message: Option<String>,
+    pub hide_empty_recent: bool,
+    pub empty_sections: EmptySections,
+    pub sections: Vec<Section>,
+    pub max_rewards: Option<usize>,
+    pub drops_per_game_cap: Option<usize>,
+    // Games pinned in the favorites section, matched ignoring case
+    pub favorites: Vec<String>,
+    // Whether the favorite games are also listed under all drops
+    pub favorites_in_all: bool,
+    // Games side by side in the all drops section, a table grid when more than one
+    pub columns: usize,
+    pub reward_references: bool,
+    // Ignore case as well as whitespace when telling whether rewards are the same
+    pub fold_reward_case: bool,
+    // Leave the campaigns listed under latest drops out of all drops
+    pub dedupe_across_sections: bool,
+    // Add the number of rewards and their summed watch time under all drops
+    pub show_totals: bool,
+    // Write the reward of a drop with only one on the drop's line
+    pub collapse_single_reward: bool,
+    // Add how long ago each campaign started under all drops
+    pub show_age: bool,
+    pub bullet: Bullet,
+    pub indent: usize,
+    pub escape: bool,
+}
+
+impl RenderOptions {
+    // Escape text taken from the API for markdown, unless --no-escape is set
+    fn escape(&self, text: &str) -> String {
+        if self.escape {
+            escape_markdown_cached(text).to_string()
+        } else {
+            text.to_string()
+        }
+    }
+
+    // Escape text for a table cell. Pipes and line breaks would end the cell or the row, so
+    // they are replaced even with --no-escape
+    fn table_cell(&self, text: &str) -> String {
+        let text = text.replace(['\r', '\n'], " ");
+        if self.escape {
+            escape_markdown_cached(&text).to_string()
+        } else {
+            text.replace('|', "\\|")
+        }
+    }
+}
+
+// Write a YAML front-matter block for static site generators. The values are quoted so
+// they are always read as strings, and an `updated` timestamp is added unless one is given
+pub fn write_front_matter(
+    entries: &[(String, String)],
+    now: DateTime<Utc>,
+    writer: &mut impl Write,
+) -> Result<()> {
+    writeln!(writer, "---").map_err(DropsError::Write)?;
+    for (key, value) in entries {
+        writeln!(writer, "{}: {}", key, yaml_string(value)).map_err(DropsError::Write)?;
+    }
+    if !entries.iter().any(|(key, _)| key == "updated") {
+        writeln!(
+            writer,
+            "updated: {}",
+            now.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+        )
+        .map_err(DropsError::Write)?;
+    }
+    writeln!(writer, "---\n").map_err(DropsError::Write)?;
+    Ok(())
+}
+
+// Quote a value as a double-quoted YAML string
+fn yaml_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Write only the headline numbers
+pub fn write_summary(
+    summary: &Summary,
+    options: &RenderOptions,
+    writer: &mut impl Write,
+) -> Result<()> {
+    let text = options.text;
+    let line = text.fill(text.summary, text.number(summary.campaigns as u64));
+    let line = text.fill(&line, text.number(summary.games as u64));
+    let line = text.fill(&line, text.number(summary.ending_within_24h as u64));
+    writeln!(writer, "{}", line).map_err(DropsError::Write)?;
+    Ok(())
+}
+
+// Write the full document: title, latest drops and all drops
+pub fn write_document(
+    games: &[ApiGame],
+    options: &RenderOptions,
+    writer: &mut impl Write,
+) -> Result<()> {
+    writeln!(writer, "# {}\n", options.text.title).map_err(DropsError::Write)?;
+
+    if games.is_empty() {
+        // Every section would be empty, so with --emit-empty-sections never only the title is left
+        if options.empty_sections == EmptySections::Always {
+            writeln!(writer, "{}", options.text.no_campaigns).map_err(DropsError::Write)?;
+        }
+        return Ok(());
+    }
+
+    for section in &options.sections {
+        match section {
+            Section::Deadlines => write_deadlines(
+                games,
+                options.pinned_deadlines.unwrap_or(0),
+                options,
+                writer,
+            )?,
+            Section::Favorites => write_favorites(games, options, writer)?,
+            Section::Recent => write_latest_drops(games, options, writer)?,
+            Section::All => write_all_games(games, options, writer)?,
+            Section::EndWeek => write_by_end_week(games, options, writer)?,
+            Section::Rewardless => write_rewardless(games, options, writer)?,
+            Section::Longest => {
+                write_by_duration(games, options.longest.unwrap_or(0), true, options, writer)?
+            }
+            Section::Shortest => {
+                write_by_duration(games, options.shortest.unwrap_or(0), false, options, writer)?
+            }
+        }
+    }
+    Ok(())
+}
+
+// Write every campaign as one flat GFM table, soonest ending first
+pub fn write_table(
+    games: &[ApiGame],
+    options: &RenderOptions,
+    writer: &mut impl Write,
+) -> Result<()> {
+    let text = options.text;
+    writeln!(writer, "# {}\n", text.title).map_err(DropsError::Write)?;
+    if games.iter().all(|game| game.drops.is_empty()) {
+        writeln!(writer, "{}", text.no_campaigns).map_err(DropsError::Write)?;
+        return Ok(());
+    }
+
+    let mut drops: Vec<(&ApiGame, &ApiDrops)> = games
+        .iter()
+        .flat_map(|game| game.drops.iter().map(move |drop| (game, drop)))
+        .collect();
+    // Ties are broken by game then drop name so the output is stable
+    drops.sort_by(|(game_a, drop_a), (game_b, drop_b)| {
+        drop_a
+            .end_at
+            .cmp(&drop_b.end_at)
+            .then_with(|| {
+                game_a
+                    .game_display_name
+                    .to_lowercase()
+                    .cmp(&game_b.game_display_name.to_lowercase())
+            })
+            .then_with(|| drop_a.name.cmp(&drop_b.name))
+    });
+
+    writeln!(
+        writer,
+        "| {} | {} | {} | {} | {} |",
+        text.table_game,
+        text.table_campaign,
+        text.table_ends,
+        text.table_rewards,
+        text.table_max_watch_time
+    )
+    .map_err(DropsError::Write)?;
+    writeln!(writer, "| --- | --- | --- | ---: | ---: |").map_err(DropsError::Write)?;
+    for (game, drop) in drops {
+        let max_minutes = drop.rewards.iter().map(|r| r.minutes_required).max();
+        writeln!(
+            writer,
+            "| {} | {} | {} | {} | {} |",
+            options.table_cell(&game.game_display_name),
+            options.table_cell(&drop.name),
+            end_label(drop.end_at, options),
+            drop.rewards.len(),
+            max_minutes.map_or("-".into(), |minutes| minutes_watched(minutes, text))
+        )
+        .map_err(DropsError::Write)?;
+    }
+    writeln!(writer).map_err(DropsError::Write)?;
+    Ok(())
+}
+
+// Write the campaigns without any time based rewards, which are easy to miss in the full list
+fn write_rewardless(
+    games: &[ApiGame],
+    options: &RenderOptions,
+    writer: &mut impl Write,
+) -> Result<()> {
+    let text = options.text;
+    writeln!(writer, "## {}\n", text.rewardless_heading).map_err(DropsError::Write)?;
+
+    let mut any = false;
+    for game in games {
+        for drop in game.drops.iter().filter(|d| d.rewards.is_empty()) {
+            writeln!(
+                writer,
+                "{}{}: {} ({})",
+                bullet(options, 0),
+                options.escape(&game.game_display_name),
+                options.escape(&drop.name),
+                end_label(drop.end_at, options)
+            )
+            .map_err(DropsError::Write)?;
+            any = true;
+        }
+    }
+    if !any {
+        writeln!(writer, "{}", text.no_rewardless).map_err(DropsError::Write)?;
+    }
+    writeln!(writer).map_err(DropsError::Write)?;
+    Ok(())
+}
+
+// Write every campaign grouped by the ISO week it ends in, earliest week first
+fn write_by_end_week(
+    games: &[ApiGame],
+    options: &RenderOptions,
+    writer: &mut impl Write,
+) -> Result<()> {
+    // Keyed by ISO year and week rather than calendar year, so the days at the turn of the
+    // year land in the same week as the rest of it
+    let mut by_week: BTreeMap<(i32, u32), Vec<(&ApiGame, &ApiDrops)>> = BTreeMap::new();
+    for game in games {
+        for drop in &game.drops {
+            let week = drop.end_at.iso_week();
+            by_week
+                .entry((week.year(), week.week()))
+                .or_default()
+                .push((game, drop));
+        }
+    }
+
+    writeln!(writer, "## {}\n", options.text.end_week_heading).map_err(DropsError::Write)?;
+    for ((year, week), mut drops) in by_week {
+        drops.sort_by_key(|(_, drop)| drop.end_at);
+        writeln!(writer, "{}-W{:02}", year, week).map_err(DropsError::Write)?;
+        for (game, drop) in drops {
+            writeln!(
+                writer,
+                "{}{}: {} ({})",
+                bullet(options, 0),
+                options.escape(&game.game_display_name),
+                options.escape(&drop.name),
+                end_label(drop.end_at, options)
+            )
+            .map_err(DropsError::Write)?;
+        }
+        writeln!(writer).map_err(DropsError::Write)?;
+    }
+    Ok(())
+}
+
+// Write the N campaigns across all games that end soonest, skipping those that already ended
+fn write_deadlines(
+    games: &[ApiGame],
+    count: usize,
+    options: &RenderOptions,
+    writer: &mut impl Write,
+) -> Result<()> {
+    let mut drops: Vec<(&ApiGame, &ApiDrops)> = games
+        .iter()
+        .flat_map(|game| game.drops.iter().map(move |drop| (game, drop)))
+        .filter(|(_, drop)| drop.end_at > options.now)
+        .collect();
+    // Ties are broken by game then drop name so the output is stable
+    drops.sort_by(|(game_a, drop_a), (game_b, drop_b)| {
+        drop_a
+            .end_at
+            .cmp(&drop_b.end_at)
+            .then_with(|| {
+                game_a
+                    .game_display_name
+                    .to_lowercase()
+                    .cmp(&game_b.game_display_name.to_lowercase())
+            })
+            .then_with(|| drop_a.name.cmp(&drop_b.name))
+    });
+
+    writeln!(writer, "## {}\n", options.text.deadlines_heading).map_err(DropsError::Write)?;
+    for (game, drop) in drops.iter().take(count) {
+        writeln!(
+            writer,
+            "{}{}: {} ({})",
+            bullet(options, 0),
+            options.escape(&game.game_display_name),
+            options.escape(&drop.name),
+            ends_in_days(drop.end_at, options.now, options.text)
+        )
+        .map_err(DropsError::Write)?;
+    }
+    writeln!(writer).map_err(DropsError::Write)?;
+    Ok(())
+}
+
+// Write the N campaigns with the longest (or shortest) span between start and end across all games
+fn write_by_duration(
+    games: &[ApiGame],
+    count: usize,
+    longest: bool,
+    options: &RenderOptions,
+    writer: &mut impl Write,
+) -> Result<()> {
+    let text = options.text;
+    let mut drops: Vec<(&ApiGame, &ApiDrops)> = games
+        .iter()
+        .flat_map(|game| game.drops.iter().map(move |drop| (game, drop)))
+        .collect();
+    // Ties are broken by game then drop name so the output is stable
+    drops.sort_by(|(game_a, drop_a), (game_b, drop_b)| {
+        let span_a = drop_a.end_at - drop_a.start_at;
+        let span_b = drop_b.end_at - drop_b.start_at;
+        let by_span = if longest {
+            span_b.cmp(&span_a)
+        } else {
+            span_a.cmp(&span_b)
+        };
+        by_span
+            .then_with(|| {
+                game_a
+                    .game_display_name
+                    .to_lowercase()
+                    .cmp(&game_b.game_display_name.to_lowercase())
+            })
+            .then_with(|| drop_a.name.cmp(&drop_b.name))
+    });
+
+    let heading = if longest {
+        text.longest_heading
+    } else {
+        text.shortest_heading
+    };
+    writeln!(writer, "## {}\n", heading).map_err(DropsError::Write)?;
+    for (game, drop) in drops.iter().take(count) {
+        writeln!(
+            writer,
+            "{}{}: {} ({})",
+            bullet(options, 0),
+            options.escape(&game.game_display_name),
+            options.escape(&drop.name),
+            format_span(drop.end_at - drop.start_at, text)
+        )
+        .map_err(DropsError::Write)?;
+    }
+    writeln!(writer).map_err(DropsError::Write)?;
+    Ok(())
+}
+
+// Write a condensed summary of the current week: how many campaigns started and end in it,
+// and the games with the most campaigns
+pub fn write_weekly_digest(
+    games: &[ApiGame],
+    week_start: WeekStart,
+    options: &RenderOptions,
+    writer: &mut impl Write,
+) -> Result<()> {
+    let text = options.text;
+    let today = options.now.date_naive();
+    let days_into_week = match week_start {
+        WeekStart::Mon => today.weekday().num_days_from_monday(),
+        WeekStart::Sun => today.weekday().num_days_from_sunday(),
+    };
+    let first_day = today - Duration::days(days_into_week.into());
+    let week_from = first_day.and_time(chrono::NaiveTime::MIN).and_utc();
+    let week_until = week_from + Duration::days(7);
+    let in_week = |time: DateTime<Utc>| time >= week_from && time < week_until;
+
+    let drops = games.iter().flat_map(|game| &game.drops);
+    let started = drops.clone().filter(|drop| in_week(drop.start_at)).count();
+    let ending = drops.filter(|drop| in_week(drop.end_at)).count();
+
+    // Games are already sorted by name, and the stable sort keeps that order for ties
+    let mut busiest: Vec<&ApiGame> = games.iter().filter(|g| !g.drops.is_empty()).collect();
+    busiest.sort_by_key(|game| std::cmp::Reverse(game.drops.len()));
+
+    writeln!(writer, "# {}\n", text.digest_title).map_err(DropsError::Write)?;
+    writeln!(
+        writer,
+        "{}\n",
+        text.fill(text.digest_week, first_day.format(text.date_format))
+    )
+    .map_err(DropsError::Write)?;
+    writeln!(
+        writer,
+        "{}{}",
+        bullet(options, 0),
+        text.fill(text.digest_started, text.number(started as u64))
+    )
+    .map_err(DropsError::Write)?;
+    writeln!(
+        writer,
+        "{}{}\n",
+        bullet(options, 0),
+        text.fill(text.digest_ending, text.number(ending as u64))
+    )
+    .map_err(DropsError::Write)?;
+
+    if busiest.is_empty() {
+        writeln!(writer, "{}", text.no_campaigns).map_err(DropsError::Write)?;
+        return Ok(());
+    }
+    writeln!(writer, "{}\n", text.digest_top_games).map_err(DropsError::Write)?;
+    for (rank, game) in busiest.iter().take(DIGEST_TOP_GAMES).enumerate() {
+        writeln!(
+            writer,
+            "{}. {} ({})",
+            rank + 1,
+            options.escape(&game.game_display_name),
+            text.fill(text.digest_campaigns, text.number(game.drops.len() as u64))
+        )
+        .map_err(DropsError::Write)?;
+    }
+    Ok(())
+}
+
+// Write the rewards requiring the most watch time across all games as a standalone list
+pub fn write_top_rewards(
+    games: &[ApiGame],
+    count: usize,
+    options: &RenderOptions,
+    writer: &mut impl Write,
+) -> Result<()> {
+    let text = options.text;
+    let mut rewards: Vec<(&ApiGame, &ApiDrops, &ApiReward)> = games
+        .iter()
+        .flat_map(|game| game.drops.iter().map(move |drop| (game, drop)))
+        .flat_map(|(game, drop)| drop.rewards.iter().map(move |reward| (game, drop, reward)))
+        .collect();
+    // Highest watch time first, ties broken by game then reward name so the output is stable
+    rewards.sort_by(|(game_a, _, reward_a), (game_b, _, reward_b)| {
+        reward_b
+            .minutes_required
+            .cmp(&reward_a.minutes_required)
+            .then_with(|| {
+                game_a
+                    .game_display_name
+                    .to_lowercase()
+                    .cmp(&game_b.game_display_name.to_lowercase())
+            })
+            .then_with(|| reward_a.name.cmp(&reward_b.name))
+    });
+
+    writeln!(writer, "# {}\n", text.top_rewards_title).map_err(DropsError::Write)?;
+
+    if rewards.is_empty() {
+        writeln!(writer, "{}", text.no_rewards).map_err(DropsError::Write)?;
+        return Ok(());
+    }
+
+    for (rank, (game, drop, reward)) in rewards.iter().take(count).enumerate() {
+        writeln!(
+            writer,
+            "{}. {} ({}) - {}: {}",
+            rank + 1,
+            options.escape(&reward.name),
+            minutes_watched(reward.minutes_required, text),
+            options.escape(&game.game_display_name),
+            options.escape(&drop.name)
+        )
+        .map_err(DropsError::Write)?;
+    }
+    Ok(())
+}
+
+// Write the list of drop campaigns that started recently, organised by date or by game
+fn write_latest_drops(
+    games: &[ApiGame],
+    options: &RenderOptions,
+    writer: &mut impl Write,
+) -> Result<()> {
+    let text = options.text;
+
+    let mut latest_updates: BTreeMap<chrono::NaiveDate, BTreeMap<&str, Vec<&ApiDrops>>> =
+        BTreeMap::new();
+    for game in games {
+        for drop in game.drops.iter().filter(|d| is_recent(d, options.now)) {
+            latest_updates
+                .entry(drop.start_at.date_naive())
+                .or_default()
+                .entry(&game.game_display_name)
+                .or_default()
+                .push(drop);
+        }
+    }
+
+    if latest_updates.is_empty()
+        && (options.hide_empty_recent || options.empty_sections == EmptySections::Never)
+    {
+        return Ok(());
+    }
+
+    writeln!(writer, "## {}\n", text.latest_heading).map_err(DropsError::Write)?;
+
+    if latest_updates.is_empty() {
+        let message = options
+            .recent_empty_message
+            .as_deref()
+            .unwrap_or(text.no_recent_campaigns);
+        writeln!(
+            writer,
+            "{}\n",
+            text.fill(message, text.number(LATEST_WINDOW_DAYS as u64))
+        )
+        .map_err(DropsError::Write)?;
+        return Ok(());
+    }
+
+    match options.recent_group {
+        RecentGroup::Date => {
+            let mut dates: Vec<_> = latest_updates.iter().collect();
+            if options.recent_order == RecentOrder::Desc {
+                dates.reverse();
+            }
+            for (date, games_for_date) in dates {
+                writeln!(writer, "{}", recent_date(*date, options)).map_err(DropsError::Write)?;
+                for (game, drops) in games_for_date {
+                    writeln!(writer, "{}{}", bullet(options, 0), options.escape(game))
+                        .map_err(DropsError::Write)?;
+                    write_latest_drop_lines(drops, options, writer)?;
+                }
+                writeln!(writer).map_err(DropsError::Write)?;
+            }
+        }
+        RecentGroup::Game => {
+            let mut by_game: BTreeMap<&str, BTreeMap<chrono::NaiveDate, &Vec<&ApiDrops>>> =
+                BTreeMap::new();
+            for (date, games_for_date) in &latest_updates {
+                for (game, drops) in games_for_date {
+                    by_game.entry(game).or_default().insert(*date, drops);
+                }
+            }
+            for (game, dates) in by_game {
+                writeln!(writer, "{}", options.escape(game)).map_err(DropsError::Write)?;
+                let mut dates: Vec<_> = dates.into_iter().collect();
+                if options.recent_order == RecentOrder::Desc {
+                    dates.reverse();
+                }
+                for (date, drops) in dates {
+                    writeln!(
+                        writer,
+                        "{}{}",
+                        bullet(options, 0),
+                        recent_date(date, options)
+                    )
+                    .map_err(DropsError::Write)?;
+                    write_latest_drop_lines(drops, options, writer)?;
+                }
+                writeln!(writer).map_err(DropsError::Write)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// A start date of the latest drops section, relative to the render time with
+// --recent-labels relative
+fn recent_date(date: NaiveDate, options: &RenderOptions) -> String {
+    let text = options.text;
+    if options.recent_labels == RecentLabels::Relative {
+        match (options.now.date_naive() - date).num_days() {
+            0 => return text.recent_today.into(),
+            1 => return text.recent_yesterday.into(),
+            _ => {}
+        }
+    }
+    date.format(text.date_format).to_string()
+}
+
+// The parts of the list that change with the time alone while the data stays the same: how
+// long ago each game was updated, the end and age of every campaign as they are written,
+// whether it is among the latest drops and the label of its start date there
+pub fn time_labels(games: &[ApiGame], options: &RenderOptions) -> Vec<String> {
+    let mut labels = Vec::new();
+    for game in games {
+        labels.extend(game_notes(game, options));
+        for drop in &game.drops {
+            labels.push(drop_dates(drop, options));
+            if is_recent(drop, options.now) {
+                labels.push(recent_date(drop.start_at.date_naive(), options));
+            } else {
+                labels.push(String::new());
+            }
+        }
+    }
+    labels
+}
+
+// Whether a drop started recently enough to be listed under latest drops
+pub fn is_recent(drop: &ApiDrops, now: DateTime<Utc>) -> bool {
+    drop.start_at > now - Duration::days(LATEST_WINDOW_DAYS)
+}
+
+// Whether all drops leaves out the recent drops, which needs the latest drops section to be
+// written too so that they are still listed somewhere
+fn dedupes_recent(options: &RenderOptions) -> bool {
+    options.dedupe_across_sections && options.sections.contains(&Section::Recent)
+}
+
+// Write the drops under a game or date of the latest drops section, one level in
+fn write_latest_drop_lines(
+    drops: &[&ApiDrops],
+    options: &RenderOptions,
+    writer: &mut impl Write,
+) -> Result<()> {
+    for drop in drops {
+        writeln!(
+            writer,
+            "{}{} ({})",
+            bullet(options, 1),
+            drop_name(drop, options),
+            end_label(drop.end_at, options)
+        )
+        .map_err(DropsError::Write)?;
+    }
+    Ok(())
+}
+
+// Write the full list of currently active drop campaigns by game
+fn write_all_games(
+    games: &[ApiGame],
+    options: &RenderOptions,
+    writer: &mut impl Write,
+) -> Result<()> {
+    let text = options.text;
+    let totals = options.show_totals.then(|| totals_line(games, text));
+    let dedupe = dedupes_recent(options);
+    let moved = dedupe
+        && games
+            .iter()
+            .flat_map(|game| &game.drops)
+            .any(|drop| is_recent(drop, options.now));
+    let games: Vec<&ApiGame> = games
+        .iter()
+        .filter(|game| options.favorites_in_all || !is_favorite(game, options))
+        .filter(|game| !dedupe || game.drops.iter().any(|drop| !is_recent(drop, options.now)))
+        .collect();
+    if games.iter().all(|game| game.drops.is_empty()) {
+        if options.empty_sections == EmptySections::Always {
+            writeln!(writer, "## {}\n", text.all_heading).map_err(DropsError::Write)?;
+            if moved {
+                write_recent_note(options, writer)?;
+            } else {
+                writeln!(writer, "{}\n", text.no_campaigns).map_err(DropsError::Write)?;
+            }
+        }
+        return Ok(());
+    }
+    if options.columns > 1 {
+        writeln!(writer, "## {}\n", text.all_heading).map_err(DropsError::Write)?;
+        if moved {
+            write_recent_note(options, writer)?;
+        }
+        write_game_grid(&games, options, writer)?;
+        if let Some(totals) = &totals {
+            writeln!(writer, "{}\n", totals).map_err(DropsError::Write)?;
+        }
+        return Ok(());
+    }
+    // Games become headings when the index is shown so its links have anchors to point at
+    let with_index = options.index && games.len() > options.index_min_games;
+    if with_index {
+        write_index(&games, options, writer)?;
+    }
+
+    let references = if options.reward_references {
+        reward_references(&games, options)
+    } else {
+        RewardReferences::default()
+    };
+
+    writeln!(writer, "## {}\n", text.all_heading).map_err(DropsError::Write)?;
+    if moved {
+        write_recent_note(options, writer)?;
+    }
+    for game in games {
+        write_game(game, with_index, dedupe, &references, options, writer)?;
+    }
+    if let Some(totals) = &totals {
+        writeln!(writer, "{}\n", totals).map_err(DropsError::Write)?;
+    }
+
+    // GFM footnote definitions for the rewards referenced above
+    if !references.names.is_empty() {
+        for (i, name) in references.names.iter().enumerate() {
+            writeln!(writer, "[^r{}]: {}", i + 1, options.escape(name))
+                .map_err(DropsError::Write)?;
+        }
+        writeln!(writer).map_err(DropsError::Write)?;
+    }
+    Ok(())
+}
+
+// Point readers of all drops at the latest drops section for the campaigns left out of it,
+// saying whether it is above or below. Only called when both sections are written
+fn write_recent_note(options: &RenderOptions, writer: &mut impl Write) -> Result<()> {
+    let text = options.text;
+    let link = format!(
+        "[{}](#{})",
+        text.latest_heading,
+        heading_anchor(text.latest_heading)
+    );
+    let position = |section| options.sections.iter().position(|s| *s == section);
+    let template = if position(Section::Recent) < position(Section::All) {
+        text.recent_above
+    } else {
+        text.recent_below
+    };
+    let note = text.fill(template, text.number(LATEST_WINDOW_DAYS as u64));
+    writeln!(writer, "{}\n", text.fill(&note, link)).map_err(DropsError::Write)?;
+    Ok(())
+}
+
+// Write the favorite games with all their drops, in the order they were given. Rewards are
+// written out in full since the footnotes of --reward-references belong to all drops
+fn write_favorites(
+    games: &[ApiGame],
+    options: &RenderOptions,
+    writer: &mut impl Write,
+) -> Result<()> {
+    writeln!(writer, "## {}\n", options.text.favorites_heading).map_err(DropsError::Write)?;
+    for favorite in &options.favorites {
+        let favorite = favorite.to_lowercase();
+        if let Some(game) = games
+            .iter()
+            .find(|game| game.game_display_name.to_lowercase() == favorite)
+        {
+            write_game(
+                game,
+                false,
+                false,
+                &RewardReferences::default(),
+                options,
+                writer,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn is_favorite(game: &ApiGame, options: &RenderOptions) -> bool {
+    let name = game.game_display_name.to_lowercase();
+    options
+        .favorites
+        .iter()
+        .any(|favorite| favorite.to_lowercase() == name)
+}
+
+// Write a game with its drops and their rewards, as a heading when the index links to it.
+// With skip_recent the drops listed under latest drops are left out
+fn write_game(
+    game: &ApiGame,
+    as_heading: bool,
+    skip_recent: bool,
+    references: &RewardReferences,
+    options: &RenderOptions,
+    writer: &mut impl Write,
+) -> Result<()> {
+    let text = options.text;
+    let notes = game_notes(game, options);
+    if as_heading {
+        // The notes go below the heading so its anchor only depends on the game name
+        writeln!(writer, "### {}\n", options.escape(&game.game_display_name))
+            .map_err(DropsError::Write)?;
+        if let Some(notes) = notes {
+            writeln!(writer, "{}\n", notes).map_err(DropsError::Write)?;
+        }
+    } else {
+        match notes {
+            Some(notes) => writeln!(
+                writer,
+                "{} ({})",
+                options.escape(&game.game_display_name),
+                notes
+            )
+            .map_err(DropsError::Write)?,
+            None => writeln!(writer, "{}", options.escape(&game.game_display_name))
+                .map_err(DropsError::Write)?,
+        }
+    }
+    let (drops, hidden) = capped_drops(game, skip_recent, options);
+    for drop in drops {
+        let end = drop_dates(drop, options);
+        let image = match drop.image_url.as_deref().and_then(link_destination) {
+            Some(url) => format!("![]({}) ", url),
+            None => String::new(),
+        };
+        let line = format!(
+            "{}{}{} ({})",
+            bullet(options, 0),
+            image,
+            drop_name(drop, options),
+            end
+        );
+        match drop.rewards.as_slice() {
+            [reward] if options.collapse_single_reward && options.max_rewards != Some(0) => {
+                writeln!(
+                    writer,
+                    "{} — {}",
+                    line,
+                    reward_item(reward, references, options)
+                )
+                .map_err(DropsError::Write)?;
+            }
+            _ => {
+                writeln!(writer, "{}", line).map_err(DropsError::Write)?;
+                write_rewards(drop, references, options, writer)?;
+            }
+        }
+    }
+    if hidden > 0 {
+        writeln!(
+            writer,
+            "{}{}",
+            bullet(options, 0),
+            text.fill(text.more_rewards, text.number(hidden as u64))
+        )
+        .map_err(DropsError::Write)?;
+    }
+    writeln!(writer).map_err(DropsError::Write)?;
+    Ok(())
+}
+
+// The end label of a drop listed under all drops, followed by its age with --show-age
+fn drop_dates(drop: &ApiDrops, options: &RenderOptions) -> String {
+    let end = end_label(drop.end_at, options);
+    if options.show_age {
+        format!(
+            "{}, {}",
+            end,
+            age_in_days(drop.start_at, options.now, options.text)
+        )
+    } else {
+        end
+    }
+}
+
+// The number of rewards of every campaign and the hours it takes to earn them all
+fn totals_line(games: &[ApiGame], text: &Translations) -> String {
+    let rewards = games
+        .iter()
+        .flat_map(|game| &game.drops)
+        .flat_map(|drop| &drop.rewards);
+    let count = rewards.clone().count();
+    let minutes: u64 = rewards
+        .map(|reward| u64::from(reward.minutes_required))
+        .sum();
+    text.fill(
+        &text.fill(text.totals, text.number(count as u64)),
+        format_hours(minutes, text),
+    )
+}
+
+// The drops of a game listed under all drops and how many --drops-per-game-cap left out.
+// Capping keeps the drops that end soonest, after leaving out the recent ones with skip_recent
+fn capped_drops<'a>(
+    game: &'a ApiGame,
+    skip_recent: bool,
+    options: &RenderOptions,
+) -> (Vec<&'a ApiDrops>, usize) {
+    let mut drops: Vec<&ApiDrops> = game
+        .drops
+        .iter()
+        .filter(|drop| !skip_recent || !is_recent(drop, options.now))
+        .collect();
+    if options.drops_per_game_cap.is_some() {
+        drops.sort_by_key(|drop| drop.end_at);
+    }
+    let hidden = match options.drops_per_game_cap {
+        Some(cap) if drops.len() > cap => drops.split_off(cap).len(),
+        _ => 0,
+    };
+    (drops, hidden)
+}
+
+// Write the games as a table with --columns cells per row, filled row by row. Each cell
+// has the game and its drops on separate lines, without the rewards
+fn write_game_grid(
+    games: &[&ApiGame],
+    options: &RenderOptions,
+    writer: &mut impl Write,
+) -> Result<()> {
+    let text = options.text;
+    let columns = options.columns;
+    writeln!(writer, "|{}", "   |".repeat(columns)).map_err(DropsError::Write)?;
+    writeln!(writer, "|{}", " --- |".repeat(columns)).map_err(DropsError::Write)?;
+    for row in games.chunks(columns) {
+        let mut cells: Vec<String> = row
+            .iter()
+            .map(|game| {
+                let name = options.table_cell(&game.game_display_name);
+                let mut lines = vec![match game_notes(game, options) {
+                    Some(notes) => format!("**{}** ({})", name, notes),
+                    None => format!("**{}**", name),
+                }];
+                let (drops, hidden) = capped_drops(game, dedupes_recent(options), options);
+                for drop in drops {
+                    let name = match drop.detail_url.as_deref().and_then(link_destination) {
+                        Some(url) => format!("[{}]({})", options.table_cell(&drop.name), url),
+                        None => options.table_cell(&drop.name),
+                    };
+                    lines.push(format!("{} ({})", name, drop_dates(drop, options)));
+                }
+                if hidden > 0 {
+                    lines.push(text.fill(text.more_rewards, text.number(hidden as u64)));
+                }
+                lines.join("<br>")
+            })
+            .collect();
+        cells.resize(columns, String::new());
+        writeln!(writer, "| {} |", cells.join(" | ")).map_err(DropsError::Write)?;
+    }
+    writeln!(writer).map_err(DropsError::Write)?;
+    Ok(())
+}
+
+// Reward names used more than once across all drops, in order of first appearance.
+// With --reward-references these are written once as footnotes and referenced by position.
+// Names are compared in their canonical form, the footnote keeping the first spelling
+#[derive(Default)]
+struct RewardReferences<'a> {
+    names: Vec<&'a str>,
+    // Position in names by canonical name, so rewards are looked up without comparing them
+    // against every footnote
+    positions: HashMap<String, usize>,
+}
+
+impl RewardReferences<'_> {
+    // The position of the footnote for a reward name, None if it has none
+    fn position(&self, name: &str, options: &RenderOptions) -> Option<usize> {
+        if self.names.is_empty() {
+            return None;
+        }
+        let canonical = canonical_reward_name(name, options.fold_reward_case);
+        self.positions.get(&canonical).copied()
+    }
+}
+
+// Collect the references of the games written under all drops
+fn reward_references<'a>(games: &[&'a ApiGame], options: &RenderOptions) -> RewardReferences<'a> {
+    let mut counts: HashMap<String, (&'a str, usize)> = HashMap::new();
+    let mut order = Vec::new();
+    for reward in games
+        .iter()
+        .flat_map(|game| &game.drops)
+        .flat_map(|drop| &drop.rewards)
+    {
+        let canonical = canonical_reward_name(&reward.name, options.fold_reward_case);
+        let (_, count) = counts
+            .entry(canonical.clone())
+            .or_insert((reward.name.as_str(), 0));
+        if *count == 0 {
+            order.push(canonical);
+        }
+        *count += 1;
+    }
+    let mut references = RewardReferences::default();
+    for canonical in order {
+        let (name, count) = counts[&canonical];
+        if count > 1 {
+            references
+                .positions
+                .insert(canonical, references.names.len());
+            references.names.push(name);
+        }
+    }
+    references
+}
+
+// Write the reward lines of a drop, cut down to --max-rewards with a note about the rest
+fn write_rewards(
+    drop: &ApiDrops,
+    references: &RewardReferences,
+    options: &RenderOptions,
+    writer: &mut impl Write,
+) -> Result<()> {
+    let text = options.text;
+    let mut rewards: Vec<(usize, &ApiReward)> = drop.rewards.iter().enumerate().collect();
+    // Truncating keeps the rewards that are quickest to earn
+    if options.max_rewards.is_some() {
+        rewards.sort_by_key(|(_, reward)| reward.minutes_required);
+    }
+    if options.by_reward_type {
+        rewards.sort_by_key(|(_, reward)| classify_reward(&reward.name));
+    }
+    let hidden = match options.max_rewards {
+        Some(max) if rewards.len() > max => rewards.split_off(max).len(),
+        _ => 0,
+    };
+    if let Some(order) = options.reward_sort {
+        match order {
+            RewardSort::Minutes => rewards.sort_by_key(|(_, reward)| reward.minutes_required),
+            RewardSort::Name => rewards.sort_by_key(|(_, reward)| reward.name.to_lowercase()),
+            RewardSort::Api => rewards.sort_by_key(|(i, _)| *i),
+        }
+        // The sorts are stable, so each type keeps the chosen order
+        if options.by_reward_type {
+            rewards.sort_by_key(|(_, reward)| classify_reward(&reward.name));
+        }
+    }
+
+    for (_, reward) in rewards {
+        writeln!(
+            writer,
+            "{}{}",
+            bullet(options, 1),
+            reward_item(reward, references, options)
+        )
+        .map_err(DropsError::Write)?;
+    }
+    if hidden > 0 {
+        writeln!(
+            writer,
+            "{}{}",
+            bullet(options, 1),
+            text.fill(text.more_rewards, text.number(hidden as u64))
+        )
+        .map_err(DropsError::Write)?;
+    }
+    Ok(())
+}
+
+// The name of a drop, linking to the campaign's page when the API gives a usable one
+fn drop_name(drop: &ApiDrops, options: &RenderOptions) -> String {
+    match drop.detail_url.as_deref().and_then(link_destination) {
+        Some(url) => format!("[{}]({})", options.escape(&drop.name), url),
+        None => options.escape(&drop.name),
+    }
+}
+
+// A URL from the API as the destination of a markdown link or image, or None unless it is
+// http(s). It is wrapped in angle brackets with anything that could end the destination, the
+// link or a table cell percent-encoded, so the URL can't inject markup of its own
+fn link_destination(url: &str) -> Option<String> {
+    let scheme = url.split_once("://")?.0;
+    if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+        return None;
+    }
+    let mut destination = String::from("<");
+    for c in url.chars() {
+        if c.is_whitespace() || c.is_control() || matches!(c, '<' | '>' | '|' | '\\') {
+            let mut buf = [0; 4];
+            for byte in c.encode_utf8(&mut buf).bytes() {
+                destination.push_str(&format!("%{:02X}", byte));
+            }
+        } else {
+            destination.push(c);
+        }
+    }
+    destination.push('>');
+    Some(destination)
+}
+
+// What is shown after a game's name: its viewers and how long ago its data changed, when the
+// API gives them
+fn game_notes(game: &ApiGame, options: &RenderOptions) -> Option<String> {
+    let text = options.text;
+    let notes: Vec<String> = [
+        game.viewer_count
+            .map(|viewers| text.fill(text.watching, format_compact_number(viewers, text))),
+        game.updated_at
+            .map(|updated| text.fill(text.updated_ago, format_elapsed(options.now - updated))),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    (!notes.is_empty()).then(|| notes.join(", "))
+}
+
+// Format a time span compactly in minutes, hours or days, e.g. 45m, 2h or 3d
+fn format_elapsed(elapsed: Duration) -> String {
+    if elapsed.num_hours() < 1 {
+        format!("{}m", elapsed.num_minutes().max(0))
+    } else if elapsed.num_hours() < 48 {
+        format!("{}h", elapsed.num_hours())
+    } else {
+        format!("{}d", elapsed.num_days())
+    }
+}
+
+// Write an alphabetical index of the games, grouped by first letter like a glossary
+fn write_index(games: &[&ApiGame], options: &RenderOptions, writer: &mut impl Write) -> Result<()> {
+    let mut by_letter: BTreeMap<String, Vec<&ApiGame>> = BTreeMap::new();
+    for game in games {
+        let letter = match game.game_display_name.chars().next() {
+            Some(c) if c.is_alphabetic() => c.to_uppercase().collect(),
+            _ => "#".to_string(),
+        };
+        by_letter.entry(letter).or_default().push(game);
+    }
+
+    writeln!(writer, "## {}\n", options.text.index_heading).map_err(DropsError::Write)?;
+    for (letter, games) in &by_letter {
+        writeln!(writer, "**{}**\n", options.escape(letter)).map_err(DropsError::Write)?;
+        for game in games {
+            writeln!(
+                writer,
+                "{}[{}](#{})",
+                bullet(options, 0),
+                options.escape(&game.game_display_name),
+                heading_anchor(&game.game_display_name)
+            )
+            .map_err(DropsError::Write)?;
+        }
+        writeln!(writer).map_err(DropsError::Write)?;
+    }
+    Ok(())
+}
+
+// The anchor GitHub generates for a heading: lowercase by Unicode rules, spaces become dashes
+// and punctuation is dropped. Letters and digits of any script are kept as they are, along
+// with combining accents, so "Pokémon" and "原神" link to "pokémon" and "原神"
+pub fn heading_anchor(heading: &str) -> String {
+    heading
+        .chars()
+        .flat_map(char::to_lowercase)
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            '-' | '_' | '\u{0300}'..='\u{036f}' => Some(c),
+            _ if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+// The start of a list item nested `level` lists deep
+fn bullet(options: &RenderOptions, level: usize) -> String {
+    format!(
+        "{}{} ",
+        " ".repeat(options.indent * level),
+        options.bullet.marker()
+    )
+}
+
+// A reward as listed under its drop, labelled with its type with --by-reward-type
+fn reward_item(
+    reward: &ApiReward,
+    references: &RewardReferences,
+    options: &RenderOptions,
+) -> String {
+    let line = reward_line(reward, references, options);
+    if options.by_reward_type {
+        format!(
+            "{}: {}",
+            options.text.reward_kind(classify_reward(&reward.name)),
+            line
+        )
+    } else {
+        line
+    }
+}
+
+// Render a single reward, using the --reward-format template when one is set.
+// Rewards in `references` are replaced by a reference to their footnote
+fn reward_line(
+    reward: &ApiReward,
+    references: &RewardReferences,
+    options: &RenderOptions,
+) -> String {
+    let text = options.text;
+    let name = match references.position(&reward.name, options) {
+        Some(i) => format!("[^r{}]", i + 1),
+        None => options.escape(&reward.name),
+    };
+    match &options.reward_format {
+        Some(template) => template.render(
+            &name,
+            &text.number(reward.minutes_required.into()),
+            &format_hours(reward.minutes_required.into(), text),
+        ),
+        None => format!(
+            "{} ({})",
+            name,
+            watch_time(reward.minutes_required, options)
+        ),
+    }
+}
+
+// Number of escaped strings remembered by escape_markdown_cached
+const ESCAPE_CACHE_CAPACITY: NonZeroUsize = NonZeroUsize::new(1024).unwrap();
+
+// Escaped strings by their input, dropping the least recently used once full. Game names are
+// escaped again for every section, heading and index link they appear in
+thread_local! {
+    static ESCAPE_CACHE: RefCell<LruCache<String, Rc<str>>> =
+        RefCell::new(LruCache::new(ESCAPE_CACHE_CAPACITY));
+}
+
+// escape_markdown remembering recent results, for text that is escaped over and over
+pub fn escape_markdown_cached(text: &str) -> Rc<str> {
+    ESCAPE_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if let Some(escaped) = cache.get(text) {
+            return Rc::clone(escaped);
+        }
+        let escaped: Rc<str> = escape_markdown(text).into();
+        cache.put(text.to_string(), Rc::clone(&escaped));
+        escaped
+    })
+}
+
+// Escape markdown special characters
+pub fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '{' | '}' | '[' | ']' | '(' | ')' | '#' | '+' | '-' | '.'
+            | '!' | '|' | '<' | '>' | '~' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Label a campaign's end, adding the exact date and time with --show-absolute-dates
+pub fn end_label(end: DateTime<Utc>, options: &RenderOptions) -> String {
+    let text = options.text;
+    let relative = ends_in_days(end, options.now, text);
+    if !options.show_absolute_dates {
+        return relative;
+    }
+    let (first, others) = options.timezones.split_first().unwrap_or((&Tz::UTC, &[]));
+    let local = end.with_timezone(first);
+    let mut label = format!(
+        "{}, {} {}",
+        relative,
+        local.format(text.date_format),
+        local.format("%H:%M %Z")
+    );
+    // The date is only repeated for zones where it falls on another day
+    for zone in others {
+        let other = end.with_timezone(zone);
+        if other.date_naive() == local.date_naive() {
+            label.push_str(&format!(" / {}", other.format("%H:%M %Z")));
+        } else {
+            label.push_str(&format!(
+                " / {} {}",
+                other.format(text.date_format),
+                other.format("%H:%M %Z")
+            ));
+        }
+    }
+    label
+}
+
+// Calculate days until end date, or since it for campaigns that already ended,
+// and format as a human-readable string
+pub fn ends_in_days(end: DateTime<Utc>, now: DateTime<Utc>, text: &Translations) -> String {
+    if end <= now {
+        let days = now.signed_duration_since(end).num_days() as i16;
+        return format_days_ago(days, text);
+    }
+    let days = end.signed_duration_since(now).num_days() as i16;
+    text.fill(text.ends, format_days_from_now(days, text))
+}
+
+// Format how long ago a campaign started, the counterpart of ends_in_days
+pub fn age_in_days(start: DateTime<Utc>, now: DateTime<Utc>, text: &Translations) -> String {
+    if start > now {
+        let days = start.signed_duration_since(now).num_days() as i16;
+        return text.fill(text.starts, format_days_from_now(days, text));
+    }
+    let days = now.signed_duration_since(start).num_days() as i16;
+    match days {
+        0 => text.started_today.into(),
+        1 => text.started_yesterday.into(),
+        _ => text.fill(text.started_days_ago, text.number(days as u64)),
+    }
+}
+
+// Format how many days ago a campaign ended
+pub fn format_days_ago(days: i16, text: &Translations) -> String {
+    match days {
+        0 => text.ended_today.into(),
+        1 => text.ended_yesterday.into(),
+        _ => text.fill(text.ended_days_ago, text.number(days as u64)),
+    }
+}
+
+// Format a number of days from now into a human-readable string - for future dates only
+pub fn format_days_from_now(days: i16, text: &Translations) -> String {
+    match days {
+        0 => text.today.into(),
+        1 => text.tomorrow.into(),
+        _ => text.fill(text.in_days, text.number(days as u64)),
+    }
+}
+
+// Format how long a campaign runs for, in days or in hours for campaigns shorter than a day
+fn format_span(span: Duration, text: &Translations) -> String {
+    if span.num_days() >= 1 {
+        text.fill(text.span_days, text.number(span.num_days() as u64))
+    } else {
+        text.fill(text.span_hours, text.number(span.num_hours().max(0) as u64))
+    }
+}
+
+// Abbreviate a large count to one decimal, e.g. 12300 as 12.3k and 4500000 as 4.5M, using
+// the decimal separator of the language
+pub fn format_compact_number(value: u64, text: &Translations) -> String {
+    const UNITS: [(u64, &str); 3] = [(1_000, "k"), (1_000_000, "M"), (1_000_000_000, "B")];
+    let Some(mut unit) = UNITS.iter().rposition(|(size, _)| value >= *size) else {
+        return text.number(value);
+    };
+    let tenths = |size: u64| (value * 10 + size / 2) / size;
+    // Rounding can carry into the next unit, 999950 is 1M rather than 1000k
+    if unit + 1 < UNITS.len() && tenths(UNITS[unit].0) >= 10_000 {
+        unit += 1;
+    }
+    let (size, suffix) = UNITS[unit];
+    let tenths = tenths(size);
+    if tenths % 10 == 0 {
+        format!("{}{}", tenths / 10, suffix)
+    } else {
+        format!(
+            "{}{}{}{}",
+            tenths / 10,
+            text.decimal_separator,
+            tenths % 10,
+            suffix
+        )
+    }
+}
+
+// Format the watch time required for a reward
+pub fn minutes_watched(minutes: u16, text: &Translations) -> String {
+    text.fill(text.minutes_watched, text.number(minutes.into()))
+}
+
+// Format the watch time of a reward in the unit chosen with --reward-unit
+fn watch_time(minutes: u16, options: &RenderOptions) -> String {
+    let text = options.text;
+    match options.reward_unit {
+        RewardUnit::Minutes => minutes_watched(minutes, text),
+        RewardUnit::Auto if minutes < 120 => minutes_watched(minutes, text),
+        RewardUnit::Auto => text.fill(text.hours_watched, format_hours(minutes.into(), text)),
+        RewardUnit::Hours => text.fill(text.hours_watched, text.decimal(f64::from(minutes) / 60.0)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{ApiDrops, ApiReward};
+    use crate::locale::Locale;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 15, 0, 0, 0).unwrap()
+    }
+
+    fn options() -> RenderOptions {
+        RenderOptions {
+            now: now(),
+            text: Locale::En.translations(),
+            pinned_deadlines: None,
+            longest: None,
+            shortest: None,
+            reward_format: None,
+            reward_unit: RewardUnit::Minutes,
+            reward_sort: None,
+            show_absolute_dates: false,
+            timezones: Vec::new(),
+            index: false,
+            index_min_games: 20,
+            recent_order: RecentOrder::Desc,
+            recent_group: RecentGroup::Date,
+            recent_labels: RecentLabels::Absolute,
+            by_reward_type: false,
+            recent_empty_message: None,
+            hide_empty_recent: false,
+            empty_sections: EmptySections::Always,
+            sections: vec![Section::Recent, Section::All],
+            max_rewards: None,
+            drops_per_game_cap: None,
+            favorites: Vec::new(),
+            favorites_in_all: true,
+            columns: 1,
+            reward_references: false,
+            fold_reward_case: false,
+            dedupe_across_sections: false,
+            show_totals: false,
+            collapse_single_reward: false,
+            show_age: false,
+            bullet: Bullet::Dash,
+            indent: 2,
+            escape: true,
+        }
+    }
+
+    // A campaign that started `started` days before now and ends `ends` days after it
+    fn drop(name: &str, started: i64, ends: i64, rewards: &[(&str, u16)]) -> ApiDrops {
+        ApiDrops {
+            name: name.to_string(),
+            start_at: now() - chrono::Duration::days(started),
+            end_at: now() + chrono::Duration::days(ends),
+            image_url: None,
+            detail_url: None,
+            rewards: rewards
+                .iter()
+                .map(|(name, minutes)| ApiReward {
+                    name: name.to_string(),
+                    minutes_required: *minutes,
+                })
+                .collect(),
+        }
+    }
+
+    fn game(name: &str, drops: Vec<ApiDrops>) -> ApiGame {
+        ApiGame {
+            game_display_name: name.to_string(),
+            viewer_count: None,
+            updated_at: None,
+            drops,
+        }
+    }
+
+    fn render(games: &[ApiGame], options: &RenderOptions) -> String {
+        let mut out = Vec::new();
+        write_document(games, options, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn index_only_appears_above_the_threshold() {
+        let games: Vec<ApiGame> = ["A", "B", "C"]
+            .iter()
+            .map(|name| game(name, vec![drop("Campaign", 30, 5, &[("Badge", 60)])]))
+            .collect();
+        let mut options = options();
+        options.index = true;
+        options.index_min_games = 3;
+        assert!(!render(&games, &options).contains("](#a)"));
+        options.index_min_games = 2;
+        assert!(render(&games, &options).contains("](#a)"));
+    }
+
+    #[test]
+    fn index_anchors_leave_out_the_updated_note() {
+        let mut updated = game("Some Game", vec![drop("Campaign", 30, 5, &[("Badge", 60)])]);
+        updated.updated_at = Some(now() - chrono::Duration::minutes(45));
+        let games = vec![updated, game("Other", vec![drop("C", 30, 5, &[("B", 60)])])];
+        let mut options = options();
+        options.index = true;
+        options.index_min_games = 1;
+        let rendered = render(&games, &options);
+        assert!(rendered.contains("](#some-game)"));
+        assert!(rendered.contains("### Some Game\n"));
+    }
+
+    #[test]
+    fn link_destinations_must_be_http_and_are_wrapped() {
+        assert_eq!(
+            link_destination("https://example.com/a b"),
+            Some("<https://example.com/a%20b>".to_string())
+        );
+        assert_eq!(
+            link_destination("HTTP://x/<y>|z"),
+            Some("<HTTP://x/%3Cy%3E%7Cz>".to_string())
+        );
+        assert_eq!(link_destination("javascript:alert(1)"), None);
+        assert_eq!(link_destination("data://x"), None);
+        assert_eq!(link_destination("/relative"), None);
+    }
+
+    #[test]
+    fn images_with_unsafe_urls_are_left_out() {
+        let mut campaign = drop("Campaign", 30, 5, &[("Badge", 60)]);
+        campaign.image_url = Some("javascript:alert(1)".to_string());
+        let mut other = drop("Other", 30, 5, &[("Badge", 60)]);
+        other.image_url = Some("https://img/x.png) [evil](https://evil".to_string());
+        let rendered = render(&[game("Game", vec![campaign, other])], &options());
+        assert!(!rendered.contains("javascript"));
+        assert!(rendered.contains("![](<https://img/x.png)%20[evil](https://evil>)"));
+    }
+
+    #[test]
+    fn rewards_used_more_than_once_become_footnotes() {
+        let games = [
+            game("A", vec![drop("One", 30, 5, &[("Hat", 60), ("Cape", 30)])]),
+            game("B", vec![drop("Two", 30, 5, &[("Hat", 120)])]),
+        ];
+        let mut options = options();
+        options.reward_references = true;
+        let rendered = render(&games, &options);
+        assert!(rendered.contains("[^r1] (60 minutes watched)"));
+        assert!(rendered.contains("[^r1] (120 minutes watched)"));
+        assert!(rendered.contains("Cape (30 minutes watched)"));
+        assert!(rendered.contains("[^r1]: Hat\n"));
+        assert!(!rendered.contains("[^r2]"));
+    }
+
+    #[test]
+    fn fingerprint_changes_when_a_campaign_ends_within_the_day() {
+        let mut campaign = drop("Campaign", 30, 0, &[("Badge", 60)]);
+        campaign.end_at = now() + Duration::hours(12);
+        let games = [game("Game", vec![campaign])];
+        let mut later = options();
+        later.now = now() + Duration::hours(13);
+        assert_ne!(
+            crate::api::fingerprint(&games, &time_labels(&games, &options())),
+            crate::api::fingerprint(&games, &time_labels(&games, &later))
+        );
+        later.now = now() + Duration::hours(1);
+        assert_eq!(
+            crate::api::fingerprint(&games, &time_labels(&games, &options())),
+            crate::api::fingerprint(&games, &time_labels(&games, &later))
+        );
+    }
+
+    #[test]
+    fn drop_links_only_use_http_urls() {
+        let mut linked = drop("Linked", 30, 5, &[("Badge", 60)]);
+        linked.detail_url = Some("https://example.com/a)b".to_string());
+        let mut script = drop("Script", 30, 5, &[("Badge", 60)]);
+        script.detail_url = Some("javascript:alert(1)".to_string());
+        let games = [game("Game", vec![linked, script])];
+        let rendered = render(&games, &options());
+        assert!(rendered.contains("[Linked](<https://example.com/a)b>)"));
+        assert!(!rendered.contains("javascript"));
+
+        let mut grid = options();
+        grid.columns = 2;
+        let rendered = render(&games, &grid);
+        assert!(rendered.contains("[Linked](<https://example.com/a)b>)"));
+        assert!(!rendered.contains("javascript"));
+    }
+
+    #[test]
+    fn cached_escapes_match_uncached_ones() {
+        let inputs = ["Plain", "*bold* _it_", "a|b", "[x](y)", "#1 <tag>", "Plain"];
+        for input in inputs.iter().cycle().take(inputs.len() * 3) {
+            assert_eq!(&*escape_markdown_cached(input), escape_markdown(input));
+        }
+    }
+
+    #[test]
+    fn the_escape_cache_stays_correct_past_its_capacity() {
+        let count = ESCAPE_CACHE_CAPACITY.get() + 10;
+        let inputs: Vec<String> = (0..count).map(|i| format!("*{}*", i)).collect();
+        for input in inputs.iter().chain(&inputs) {
+            assert_eq!(&*escape_markdown_cached(input), escape_markdown(input));
+        }
+        ESCAPE_CACHE.with(|cache| assert_eq!(cache.borrow().len(), ESCAPE_CACHE_CAPACITY.get()));
+    }
+
+    #[test]
+    fn an_empty_list_respects_emit_empty_sections() {
+        let rendered = render(&[], &options());
+        assert!(rendered.contains(options().text.no_campaigns));
+        let mut never = options();
+        never.empty_sections = EmptySections::Never;
+        assert_eq!(render(&[], &never), format!("# {}\n\n", never.text.title));
+    }
+
+    #[test]
+    fn fingerprint_changes_with_the_updated_note_and_recent_labels() {
+        let mut updated = game("Game", vec![drop("Campaign", 0, 5, &[("Badge", 60)])]);
+        updated.updated_at = Some(now() - Duration::minutes(45));
+        let games = [updated];
+        let mut later = options();
+        later.now = now() + Duration::minutes(1);
+        assert_ne!(
+            crate::api::fingerprint(&games, &time_labels(&games, &options())),
+            crate::api::fingerprint(&games, &time_labels(&games, &later))
+        );
+
+        let games = [game("Game", vec![drop("Campaign", 0, 5, &[("Badge", 60)])])];
+        let mut today = options();
+        today.recent_labels = RecentLabels::Relative;
+        let mut tomorrow = today.clone();
+        tomorrow.now = now() + Duration::days(1);
+        assert_ne!(
+            time_labels(&games, &today)[1],
+            time_labels(&games, &tomorrow)[1]
+        );
+    }
+
+    #[test]
+    fn the_dedupe_note_says_where_the_latest_drops_are() {
+        let games = [game(
+            "Game",
+            vec![
+                drop("New", 1, 5, &[("Badge", 60)]),
+                drop("Old", 30, 5, &[("Badge", 60)]),
+            ],
+        )];
+        let mut options = options();
+        assert_eq!(render(&games, &options).matches("New (ends in").count(), 2);
+        options.dedupe_across_sections = true;
+        let rendered = render(&games, &options);
+        assert_eq!(rendered.matches("New (ends in").count(), 1);
+        assert_eq!(rendered.matches("Old (ends in").count(), 1);
+        assert!(rendered.contains("are only listed above, under [Latest"));
+        options.sections = vec![Section::All, Section::Recent];
+        assert!(render(&games, &options).contains("are only listed below, under [Latest"));
+        options.sections = vec![Section::All];
+        let rendered = render(&games, &options);
+        assert!(!rendered.contains("only listed"));
+        assert!(rendered.contains("New"));
+    }
+
+    #[test]
+    fn reward_references_match_case_and_whitespace_variants() {
+        let games = [
+            game("A", vec![drop("One", 30, 5, &[("Gold  Skin", 60)])]),
+            game("B", vec![drop("Two", 30, 5, &[(" gold skin", 90)])]),
+            game("C", vec![drop("Three", 30, 5, &[("Gold Skin\t", 120)])]),
+        ];
+        let mut options = options();
+        options.reward_references = true;
+        let rendered = render(&games, &options);
+        assert!(rendered.contains("[^r1] (60 minutes watched)"));
+        assert!(rendered.contains("gold skin (90 minutes watched)"));
+        assert!(rendered.contains("[^r1] (120 minutes watched)"));
+
+        options.fold_reward_case = true;
+        let rendered = render(&games, &options);
+        assert!(rendered.contains("[^r1] (90 minutes watched)"));
+        assert!(rendered.contains("[^r1]: Gold  Skin\n"));
+        assert!(!rendered.contains("[^r2]"));
+    }
+
+    #[test]
+    fn recent_order_controls_which_date_comes_first() {
+        let games = vec![
+            game("Older", vec![drop("Campaign", 3, 5, &[("Badge", 60)])]),
+            game("Newer", vec![drop("Campaign", 1, 5, &[("Badge", 60)])]),
+        ];
+        let mut options = options();
+        options.sections = vec![Section::Recent];
+        let rendered = render(&games, &options);
+        assert!(rendered.find("Newer").unwrap() < rendered.find("Older").unwrap());
+        options.recent_order = RecentOrder::Asc;
+        let rendered = render(&games, &options);
+        assert!(rendered.find("Older").unwrap() < rendered.find("Newer").unwrap());
+    }
+
+    #[test]
+    fn rewards_are_grouped_and_labelled_by_type() {
+        let games = vec![game(
+            "Game",
+            vec![drop(
+                "Campaign",
+                30,
+                5,
+                &[("Gold Skin", 30), ("Badge", 60), ("Hype Emote", 90)],
+            )],
+        )];
+        let mut options = options();
+        options.sections = vec![Section::All];
+        options.by_reward_type = true;
+        let rendered = render(&games, &options);
+        let emote = rendered.find("Emote: Hype Emote").unwrap();
+        let badge = rendered.find("Badge: Badge").unwrap();
+        let item = rendered.find("In-game item: Gold Skin").unwrap();
+        assert!(emote < badge && badge < item);
+    }
+
+    #[test]
+    fn end_weeks_are_iso_weeks_in_order() {
+        let games = vec![
+            game("Later", vec![drop("Campaign", 30, 5, &[("Badge", 60)])]),
+            // Sunday 3 January 2027 is still in the last ISO week of 2026
+            game("New Year", vec![drop("Campaign", 30, 80, &[("Badge", 60)])]),
+            game("Soon", vec![drop("Campaign", 30, 1, &[("Badge", 60)])]),
+        ];
+        let mut options = options();
+        options.sections = vec![Section::EndWeek];
+        let rendered = render(&games, &options);
+        let w42 = rendered.find("2026-W42\n").unwrap();
+        let w43 = rendered.find("2026-W43\n").unwrap();
+        let w53 = rendered.find("2026-W53\n").unwrap();
+        assert!(w42 < rendered.find("Soon").unwrap());
+        assert!(w42 < w43 && w43 < rendered.find("Later").unwrap());
+        assert!(w43 < w53 && w53 < rendered.find("New Year").unwrap());
+        assert!(!rendered.contains("2027-W"));
+    }
+
+    #[test]
+    fn max_rewards_keeps_the_quickest_and_counts_the_rest() {
+        let rewards = [
+            ("Slow", 240),
+            ("Quick", 30),
+            ("Middle", 120),
+            ("Other", 180),
+        ];
+        let games = vec![game("Game", vec![drop("Campaign", 30, 5, &rewards)])];
+        let mut options = options();
+        options.sections = vec![Section::All];
+        options.max_rewards = Some(2);
+        let rendered = render(&games, &options);
+        assert!(rendered.contains("Quick") && rendered.contains("Middle"));
+        assert!(!rendered.contains("Slow") && !rendered.contains("Other"));
+        assert!(rendered.contains("...and 2 more"));
+
+        options.max_rewards = Some(4);
+        assert!(!render(&games, &options).contains("more"));
+    }
+
+    #[test]
+    fn end_labels_count_days_on_both_sides_of_now() {
+        let text = Locale::En.translations();
+        let label = |hours| ends_in_days(now() + chrono::Duration::hours(hours), now(), text);
+        assert_eq!(label(0), "ended today");
+        assert_eq!(label(-23), "ended today");
+        assert_eq!(label(-24), "ended yesterday");
+        assert_eq!(label(-72), "ended 3 days ago");
+        assert_eq!(label(1), "ends today");
+        assert_eq!(label(24), "ends tomorrow");
+        assert_eq!(label(72), "ends in 3 days");
+    }
+
+    #[test]
+    fn bullets_and_indents_are_the_same_in_both_sections() {
+        let rewards = [("Badge", 60), ("Emote", 90)];
+        let games = vec![game("Game", vec![drop("Campaign", 1, 5, &rewards)])];
+        let mut options = options();
+        options.bullet = Bullet::Star;
+        options.indent = 4;
+        let rendered = render(&games, &options);
+        assert!(rendered.contains("2026-10-14\n* Game\n    * Campaign (ends in 5 days)\n"));
+        assert!(rendered.contains(
+            "Game\n* Campaign (ends in 5 days)\n    * Badge (60 minutes watched)\n    * Emote"
+        ));
+        assert!(!rendered.contains("- "));
+    }
+
+    #[test]
+    fn large_counts_are_abbreviated() {
+        let en = Locale::En.translations();
+        assert_eq!(format_compact_number(999, en), "999");
+        assert_eq!(format_compact_number(1_000, en), "1k");
+        assert_eq!(format_compact_number(12_345, en), "12.3k");
+        assert_eq!(format_compact_number(999_950, en), "1M");
+        assert_eq!(format_compact_number(4_500_000, en), "4.5M");
+        assert_eq!(format_compact_number(2_000_000_000, en), "2B");
+        assert_eq!(
+            format_compact_number(12_345, Locale::De.translations()),
+            "12,3k"
+        );
+    }
+
+    #[test]
+    fn viewer_counts_are_shown_below_the_game_name() {
+        let mut watched = game("Watched", vec![drop("Campaign", 30, 5, &[("Badge", 60)])]);
+        watched.viewer_count = Some(12_300);
+        let games = vec![
+            watched,
+            game("Unknown", vec![drop("C", 30, 5, &[("B", 60)])]),
+        ];
+        let mut options = options();
+        options.sections = vec![Section::All];
+        let rendered = render(&games, &options);
+        assert!(rendered.contains("12.3k watching"));
+        assert_eq!(rendered.matches("watching").count(), 1);
+    }
+
+    #[test]
+    fn names_are_only_left_raw_without_escaping() {
+        let games = vec![game(
+            "*Bold* Game",
+            vec![drop("Some_Drop", 30, 5, &[("Badge", 60)])],
+        )];
+        let mut options = options();
+        options.sections = vec![Section::All];
+        let escaped = render(&games, &options);
+        assert!(escaped.contains("\\*Bold\\* Game") && escaped.contains("Some\\_Drop"));
+        options.escape = false;
+        let raw = render(&games, &options);
+        assert!(raw.contains("*Bold* Game") && raw.contains("Some_Drop"));
+        assert!(!raw.contains('\\'));
+    }
+
+    #[test]
+    fn campaigns_without_rewards_are_collected_in_their_own_section() {
+        let games = vec![game(
+            "Game",
+            vec![
+                drop("Rewarded", 30, 5, &[("Badge", 60)]),
+                drop("Empty", 30, 5, &[]),
+            ],
+        )];
+        let mut options = options();
+        options.sections = vec![Section::Rewardless, Section::All];
+        let rendered = render(&games, &options);
+        let (rewardless, all) = rendered.split_once("## All drops").unwrap();
+        assert!(rewardless.contains("- Game: Empty (ends in 5 days)"));
+        assert!(!rewardless.contains("Rewarded"));
+        // They are still listed with the rest
+        assert!(all.contains("Empty") && all.contains("Rewarded"));
+
+        let rewarded = vec![game(
+            "Game",
+            vec![drop("Rewarded", 30, 5, &[("Badge", 60)])],
+        )];
+        assert!(render(&rewarded, &options).contains("Every active campaign has rewards."));
+    }
+
+    #[test]
+    fn the_table_is_ordered_by_end_and_escapes_pipes() {
+        let rewards = [("Badge", 60), ("Emote", 120)];
+        let games = vec![
+            game("Later | Game", vec![drop("Drop|One", 30, 5, &rewards)]),
+            game(
+                "Sooner",
+                vec![drop("B", 30, 2, &[]), drop("A", 30, 2, &[("X", 30)])],
+            ),
+        ];
+        let table = |options: &RenderOptions| {
+            let mut out = Vec::new();
+            write_table(&games, options, &mut out).unwrap();
+            let rendered = String::from_utf8(out).unwrap();
+            rendered
+                .lines()
+                .skip(4)
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+        let rows = table(&options());
+        assert_eq!(
+            rows[0],
+            "| Sooner | A | ends in 2 days | 1 | 30 minutes watched |"
+        );
+        assert_eq!(rows[1], "| Sooner | B | ends in 2 days | 0 | - |");
+        assert!(rows[2].starts_with("| Later \\| Game | Drop\\|One | ends in 5 days | 2 | 120"));
+
+        let mut raw = options();
+        raw.escape = false;
+        assert!(table(&raw)[2].starts_with("| Later \\| Game | Drop\\|One |"));
+    }
+
+    #[test]
+    fn end_dates_are_given_in_every_time_zone() {
+        let end = now() + chrono::Duration::days(5) + chrono::Duration::hours(18);
+        let mut options = options();
+        options.show_absolute_dates = true;
+        options.timezones = vec![Tz::UTC, chrono_tz::America::Los_Angeles];
+        assert_eq!(
+            end_label(end, &options),
+            "ends in 5 days, 2026-10-20 18:00 UTC / 11:00 PDT"
+        );
+        // The date is repeated when it differs in the other zone
+        assert_eq!(
+            end_label(end - chrono::Duration::hours(18), &options),
+            "ends in 5 days, 2026-10-20 00:00 UTC / 2026-10-19 17:00 PDT"
+        );
+    }
+
+    #[test]
+    fn the_drop_cap_applies_to_each_game_separately() {
+        let games = vec![
+            game(
+                "Busy",
+                vec![
+                    drop("Last", 30, 9, &[("Badge", 60)]),
+                    drop("First", 30, 1, &[("Badge", 60)]),
+                    drop("Middle", 30, 5, &[("Badge", 60)]),
+                ],
+            ),
+            game("Quiet", vec![drop("Only", 30, 3, &[("Badge", 60)])]),
+        ];
+        let mut options = options();
+        options.sections = vec![Section::All];
+        options.drops_per_game_cap = Some(1);
+        let rendered = render(&games, &options);
+        let (busy, quiet) = rendered.split_once("Quiet").unwrap();
+        assert!(busy.contains("First") && !busy.contains("Middle") && !busy.contains("Last"));
+        assert!(busy.contains("...and 2 more"));
+        assert!(quiet.contains("Only") && !quiet.contains("more"));
+    }
+
+    #[test]
+    fn anchors_keep_unicode_letters_like_github() {
+        assert_eq!(heading_anchor("Pokémon Scarlet"), "pokémon-scarlet");
+        assert_eq!(heading_anchor("ÉCOLE"), "école");
+        // A decomposed accent stays on its letter
+        assert_eq!(heading_anchor("Poke\u{301}mon"), "poke\u{301}mon");
+        assert_eq!(heading_anchor("原神"), "原神");
+        assert_eq!(heading_anchor("ゼルダの伝説: 王国"), "ゼルダの伝説-王国");
+        assert_eq!(
+            heading_anchor("Tom Clancy's The Division 2"),
+            "tom-clancys-the-division-2"
+        );
+        assert_eq!(
+            heading_anchor("Grand Theft Auto V (GTA_V)"),
+            "grand-theft-auto-v-gta_v"
+        );
+    }
+
+    #[test]
+    fn sections_are_written_in_the_given_order() {
+        let games = vec![game("Game", vec![drop("Campaign", 1, 5, &[("Badge", 60)])])];
+        let mut options = options();
+        let rendered = render(&games, &options);
+        assert!(rendered.find("## Latest drops").unwrap() < rendered.find("## All drops").unwrap());
+        options.sections = vec![Section::All, Section::Recent];
+        let rendered = render(&games, &options);
+        assert!(rendered.find("## All drops").unwrap() < rendered.find("## Latest drops").unwrap());
+        options.sections = vec![Section::All];
+        assert!(!render(&games, &options).contains("## Latest drops"));
+    }
+
+    #[test]
+    fn latest_drops_nest_by_date_or_by_game() {
+        let games = vec![
+            game(
+                "Alpha",
+                vec![
+                    drop("Old", 3, 5, &[("Badge", 60)]),
+                    drop("New", 1, 5, &[("Badge", 60)]),
+                ],
+            ),
+            game("Beta", vec![drop("Mid", 1, 5, &[("Badge", 60)])]),
+        ];
+        let mut options = options();
+        options.sections = vec![Section::Recent];
+        let latest = |options: &RenderOptions| {
+            let rendered = render(&games, options);
+            rendered
+                .split_once("## Latest drops\n\n")
+                .unwrap()
+                .1
+                .to_string()
+        };
+        assert_eq!(
+            latest(&options),
+            "2026-10-14\n- Alpha\n  - New (ends in 5 days)\n- Beta\n  - Mid (ends in 5 days)\n\n\
+             2026-10-12\n- Alpha\n  - Old (ends in 5 days)\n\n"
+        );
+        options.recent_group = RecentGroup::Game;
+        assert_eq!(
+            latest(&options),
+            "Alpha\n- 2026-10-14\n  - New (ends in 5 days)\n- 2026-10-12\n  - Old (ends in 5 days)\n\n\
+             Beta\n- 2026-10-14\n  - Mid (ends in 5 days)\n\n"
+        );
+    }
+
+    #[test]
+    fn columns_fill_the_grid_row_by_row() {
+        let games: Vec<ApiGame> = ["A", "B", "C", "D", "E"]
+            .iter()
+            .map(|name| game(name, vec![drop("Drop", 30, 5, &[("Badge", 60)])]))
+            .collect();
+        let mut options = options();
+        options.sections = vec![Section::All];
+        options.columns = 2;
+        let cell = |name: &str| format!("**{}**<br>Drop (ends in 5 days)", name);
+        let rendered = render(&games, &options);
+        let rows: Vec<&str> = rendered.lines().filter(|l| l.contains("<br>")).collect();
+        assert_eq!(
+            rows,
+            [
+                format!("| {} | {} |", cell("A"), cell("B")),
+                format!("| {} | {} |", cell("C"), cell("D")),
+                format!("| {} |  |", cell("E")),
+            ]
+        );
+        options.columns = 1;
+        assert!(!render(&games, &options).contains("<br>"));
+    }
+
+    #[test]
+    fn favorites_come_first_in_the_order_given() {
+        let games: Vec<ApiGame> = ["Apex Legends", "Rust", "Valorant"]
+            .iter()
+            .map(|name| game(name, vec![drop("Drop", 30, 5, &[("Badge", 60)])]))
+            .collect();
+        let mut options = options();
+        options.sections = vec![Section::Favorites, Section::All];
+        options.favorites = vec!["VALORANT".to_string(), "apex legends".to_string()];
+        let rendered = render(&games, &options);
+        let (favorites, all) = rendered.split_once("## All drops").unwrap();
+        let valorant = favorites.find("Valorant").unwrap();
+        assert!(valorant < favorites.find("Apex Legends").unwrap());
+        assert!(!favorites.contains("Rust"));
+        assert!(all.contains("Apex Legends") && all.contains("Valorant"));
+
+        options.favorites_in_all = false;
+        let rendered = render(&games, &options);
+        let all = rendered.split_once("## All drops").unwrap().1;
+        assert!(all.contains("Rust") && !all.contains("Apex") && !all.contains("Valorant"));
+    }
+
+    #[test]
+    fn ages_count_days_since_the_start() {
+        let text = Locale::En.translations();
+        let age = |hours| age_in_days(now() - chrono::Duration::hours(hours), now(), text);
+        assert_eq!(age(0), "started today");
+        assert_eq!(age(23), "started today");
+        assert_eq!(age(24), "started yesterday");
+        assert_eq!(age(47), "started yesterday");
+        assert_eq!(age(7 * 24), "started 7 days ago");
+        assert_eq!(age(-48), "starts in 2 days");
+
+        let games = vec![game("Game", vec![drop("Campaign", 7, 5, &[("Badge", 60)])])];
+        let mut options = options();
+        options.sections = vec![Section::All];
+        assert!(!render(&games, &options).contains("started"));
+        options.show_age = true;
+        assert!(render(&games, &options).contains("started 7 days ago"));
+    }
+
+    #[test]
+    fn watch_times_use_the_chosen_unit() {
+        let mut options = options();
+        let cases = [
+            (
+                RewardUnit::Minutes,
+                ["45 minutes", "60 minutes", "150 minutes"],
+            ),
+            (RewardUnit::Hours, ["0.8 hours", "1.0 hours", "2.5 hours"]),
+            (RewardUnit::Auto, ["45 minutes", "60 minutes", "2.5 hours"]),
+        ];
+        for (unit, expected) in cases {
+            options.reward_unit = unit;
+            let formatted = [45, 60, 150].map(|minutes| watch_time(minutes, &options));
+            assert_eq!(formatted, expected.map(|time| format!("{} watched", time)));
+        }
+    }
+
+    #[test]
+    fn rewards_are_sorted_by_minutes_name_or_response_order() {
+        let rewards = [("Charm", 120), ("banner", 240), ("Avatar", 30)];
+        let games = vec![game("Game", vec![drop("Campaign", 30, 5, &rewards)])];
+        let mut options = options();
+        options.sections = vec![Section::All];
+        let mut order = |sort| {
+            options.reward_sort = sort;
+            let rendered = render(&games, &options);
+            let mut names: Vec<_> = ["Charm", "banner", "Avatar"].into_iter().collect();
+            names.sort_by_key(|name| rendered.find(name).unwrap());
+            names
+        };
+        assert_eq!(
+            order(Some(RewardSort::Minutes)),
+            ["Avatar", "Charm", "banner"]
+        );
+        assert_eq!(order(Some(RewardSort::Name)), ["Avatar", "banner", "Charm"]);
+        assert_eq!(order(Some(RewardSort::Api)), ["Charm", "banner", "Avatar"]);
+        assert_eq!(order(None), ["Charm", "banner", "Avatar"]);
+    }
+
+    #[test]
+    fn single_rewards_are_folded_onto_the_drop_line() {
+        let games = vec![game(
+            "Game",
+            vec![
+                drop("Single", 30, 5, &[("Badge", 60)]),
+                drop("Multi", 30, 5, &[("Badge", 60), ("Emote", 120)]),
+            ],
+        )];
+        let mut options = options();
+        options.sections = vec![Section::All];
+        let nested = render(&games, &options);
+        assert!(nested.contains("- Single (ends in 5 days)\n  - Badge (60 minutes watched)\n"));
+        options.collapse_single_reward = true;
+        let collapsed = render(&games, &options);
+        assert!(collapsed.contains("- Single (ends in 5 days) — Badge (60 minutes watched)\n"));
+        assert!(collapsed.contains(
+            "- Multi (ends in 5 days)\n  - Badge (60 minutes watched)\n  - Emote (120 minutes watched)\n"
+        ));
+    }
+
+    #[test]
+    fn totals_sum_the_rewards_and_watch_time() {
+        let games = vec![
+            game(
+                "A",
+                vec![drop("One", 30, 5, &[("Badge", 60), ("Emote", 120)])],
+            ),
+            game(
+                "B",
+                vec![
+                    drop("Two", 30, 5, &[("Skin", 90)]),
+                    drop("Three", 30, 5, &[]),
+                ],
+            ),
+        ];
+        let mut options = options();
+        options.sections = vec![Section::All];
+        assert!(!render(&games, &options).contains("in total"));
+        options.show_totals = true;
+        let rendered = render(&games, &options);
+        let all = rendered.split_once("## All drops").unwrap().1;
+        // 270 minutes over three rewards
+        assert!(all.contains("3 rewards and 4.5 hours of watch time in total."));
+    }
+
+    #[test]
+    fn relative_labels_switch_at_midnight() {
+        let games = vec![
+            game("Just Now", vec![drop("Campaign", 0, 5, &[("Badge", 60)])]),
+            game("Before Midnight", {
+                let mut campaign = drop("Campaign", 0, 5, &[("Badge", 60)]);
+                campaign.start_at -= chrono::Duration::minutes(1);
+                vec![campaign]
+            }),
+            game("Two Days", vec![drop("Campaign", 2, 5, &[("Badge", 60)])]),
+        ];
+        let mut options = options();
+        options.sections = vec![Section::Recent];
+        options.recent_labels = RecentLabels::Relative;
+        let rendered = render(&games, &options);
+        assert!(rendered.contains("Today\n- Just Now\n"));
+        assert!(rendered.contains("Yesterday\n- Before Midnight\n"));
+        assert!(rendered.contains("2026-10-13\n- Two Days\n"));
+
+        options.recent_labels = RecentLabels::Absolute;
+        let rendered = render(&games, &options);
+        assert!(rendered.contains("2026-10-15\n- Just Now\n"));
+        assert!(!rendered.contains("Today"));
+    }
+
+    #[test]
+    fn pinned_deadlines_break_ties_by_game_then_drop() {
+        let games = vec![
+            game("beta", vec![drop("Second", 30, 2, &[("Badge", 60)])]),
+            game(
+                "Alpha",
+                vec![
+                    drop("Zulu", 30, 2, &[("Badge", 60)]),
+                    drop("Later", 30, 9, &[("Badge", 60)]),
+                    drop("Ended", 30, -1, &[("Badge", 60)]),
+                ],
+            ),
+            game("Gamma", vec![drop("Soonest", 30, 1, &[("Badge", 60)])]),
+        ];
+        let mut options = options();
+        options.sections = vec![Section::Deadlines];
+        options.pinned_deadlines = Some(3);
+        let rendered = render(&games, &options);
+        let deadlines = rendered.split_once("## Next deadlines\n\n").unwrap().1;
+        assert_eq!(
+            deadlines,
+            "- Gamma: Soonest (ends tomorrow)\n\
+             - Alpha: Zulu (ends in 2 days)\n\
+             - beta: Second (ends in 2 days)\n\n"
+        );
+    }
+
+    #[test]
+    fn top_rewards_break_ties_by_game_then_reward_name() {
+        let games = [
+            game(
+                "Beta",
+                vec![drop("Beta Drops", 1, 5, &[("Skin", 120), ("Badge", 120)])],
+            ),
+            game(
+                "alpha",
+                vec![drop(
+                    "Alpha Drops",
+                    1,
+                    5,
+                    &[("Spray", 120), ("Emote", 240), ("Hat", 30)],
+                )],
+            ),
+        ];
+        let mut out = Vec::new();
+        write_top_rewards(&games, 4, &options(), &mut out).unwrap();
+        let rendered = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = rendered.lines().skip(2).collect();
+        assert_eq!(
+            lines,
+            [
+                "1. Emote (240 minutes watched) - alpha: Alpha Drops",
+                "2. Spray (120 minutes watched) - alpha: Alpha Drops",
+                "3. Badge (120 minutes watched) - Beta: Beta Drops",
+                "4. Skin (120 minutes watched) - Beta: Beta Drops",
+            ]
+        );
+    }
+
+    #[test]
+    fn duration_sections_break_ties_by_game_then_drop_name() {
+        let games = [
+            game("Zeta", vec![drop("Season", 10, 10, &[("Badge", 60)])]),
+            game(
+                "alpha",
+                vec![
+                    drop("Second", 10, 10, &[("Badge", 60)]),
+                    drop("First", 10, 10, &[("Badge", 60)]),
+                    drop("Short", 1, 1, &[("Badge", 60)]),
+                ],
+            ),
+        ];
+        let mut options = options();
+        options.longest = Some(3);
+        options.sections = vec![Section::Longest];
+        let rendered = render(&games, &options);
+        let first = rendered.find("alpha: First").unwrap();
+        let second = rendered.find("alpha: Second").unwrap();
+        let zeta = rendered.find("Zeta: Season").unwrap();
+        assert!(first < second && second < zeta);
+        assert!(!rendered.contains("Short"));
+
+        options.shortest = Some(2);
+        options.sections = vec![Section::Shortest];
+        let rendered = render(&games, &options);
+        let short = rendered.find("alpha: Short").unwrap();
+        let first = rendered.find("alpha: First").unwrap();
+        assert!(short < first);
+        assert!(!rendered.contains("Second") && !rendered.contains("Zeta"));
+    }
+}
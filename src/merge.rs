@@ -0,0 +1,159 @@
+//! Reconciling drop campaigns that appear more than once, e.g. across sources or pages
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::api::{ApiDrops, ApiGame};
+use crate::error::{DropsError, Result};
+
+// Old game names mapped to the name they are known by now, read from a TOML file of
+// "Old Name" = "Canonical Name" lines
+#[derive(Debug, Default)]
+pub struct AliasMap {
+    aliases: HashMap<String, String>,
+}
+
+impl AliasMap {
+    pub fn load(path: &Path) -> Result<AliasMap> {
+        let contents = fs::read_to_string(path).map_err(DropsError::io("read", path))?;
+        let aliases = toml::from_str(&contents).map_err(|source| DropsError::Toml {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Ok(AliasMap { aliases })
+    }
+
+    // Rename aliased games to their canonical name, merge_games then combines their drops
+    pub fn apply(&self, games: &mut [ApiGame]) {
+        for game in games {
+            if let Some(canonical) = self.aliases.get(&game.game_display_name) {
+                game.game_display_name = canonical.clone();
+            }
+        }
+    }
+}
+
+// Merge games with the same name and collapse the drops that match on name and start time,
+// keeping the one with the latest end date since the backend may have extended it in between.
+// A merged game keeps the highest viewer count, as summing would count a game listed by
+// several sources more than once. The first occurrence of a game or drop decides where it appears in the result
+pub fn merge_games(games: Vec<ApiGame>) -> Vec<ApiGame> {
+    let mut merged: Vec<ApiGame> = Vec::with_capacity(games.len());
+    let mut game_index: HashMap<String, usize> = HashMap::new();
+    for game in games {
+        match game_index.get(&game.game_display_name) {
+            Some(&i) => {
+                merged[i].updated_at = merged[i].updated_at.max(game.updated_at);
+                merged[i].viewer_count = merged[i].viewer_count.max(game.viewer_count);
+                merged[i].drops.extend(game.drops);
+            }
+            None => {
+                game_index.insert(game.game_display_name.clone(), merged.len());
+                merged.push(game);
+            }
+        }
+    }
+
+    for game in &mut merged {
+        let drops = std::mem::take(&mut game.drops);
+        let mut drop_index = HashMap::new();
+        for drop in drops {
+            let key = (drop.name.clone(), drop.start_at);
+            match drop_index.get(&key) {
+                Some(&i) => {
+                    let kept: &mut ApiDrops = &mut game.drops[i];
+                    if drop.end_at > kept.end_at {
+                        *kept = drop;
+                    }
+                }
+                None => {
+                    drop_index.insert(key, game.drops.len());
+                    game.drops.push(drop);
+                }
+            }
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::ApiReward;
+    use chrono::{DateTime, TimeZone, Utc};
+
+    fn date(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, day, 0, 0, 0).unwrap()
+    }
+
+    fn drop(name: &str, start: u32, end: u32) -> ApiDrops {
+        ApiDrops {
+            name: name.to_string(),
+            start_at: date(start),
+            end_at: date(end),
+            image_url: None,
+            detail_url: None,
+            rewards: vec![ApiReward {
+                name: "Badge".to_string(),
+                minutes_required: 60,
+            }],
+        }
+    }
+
+    fn game(name: &str, viewers: Option<u64>, drops: Vec<ApiDrops>) -> ApiGame {
+        ApiGame {
+            game_display_name: name.to_string(),
+            viewer_count: viewers,
+            updated_at: None,
+            drops,
+        }
+    }
+
+    #[test]
+    fn games_with_the_same_name_are_merged_in_order() {
+        let merged = merge_games(vec![
+            game("A", None, vec![drop("One", 1, 10)]),
+            game("B", None, vec![drop("Two", 1, 10)]),
+            game("A", None, vec![drop("Three", 1, 10)]),
+        ]);
+        let names: Vec<_> = merged
+            .iter()
+            .map(|g| g.game_display_name.as_str())
+            .collect();
+        assert_eq!(names, ["A", "B"]);
+        let drops: Vec<_> = merged[0].drops.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(drops, ["One", "Three"]);
+    }
+
+    #[test]
+    fn duplicate_drops_keep_the_latest_end_date() {
+        let merged = merge_games(vec![
+            game("A", None, vec![drop("One", 1, 10)]),
+            game("A", None, vec![drop("One", 1, 20), drop("One", 2, 10)]),
+        ]);
+        let drops: Vec<_> = merged[0]
+            .drops
+            .iter()
+            .map(|d| (d.start_at, d.end_at))
+            .collect();
+        assert_eq!(drops, [(date(1), date(20)), (date(2), date(10))]);
+    }
+
+    #[test]
+    fn aliased_games_keep_the_highest_viewer_count() {
+        let mut games = vec![
+            game("Old Name", Some(100), vec![drop("One", 1, 10)]),
+            game("New Name", Some(250), vec![drop("Two", 1, 10)]),
+            game("Other", None, vec![drop("Three", 1, 10)]),
+        ];
+        let aliases = AliasMap {
+            aliases: HashMap::from([("Old Name".to_string(), "New Name".to_string())]),
+        };
+        aliases.apply(&mut games);
+        let merged = merge_games(games);
+        assert_eq!(merged[0].game_display_name, "New Name");
+        assert_eq!(merged[0].viewer_count, Some(250));
+        assert_eq!(merged[0].drops.len(), 2);
+        assert_eq!(merged[1].viewer_count, None);
+    }
+}
@@ -0,0 +1,175 @@
+//! A markdown report of the lines a run would change in a rendered file, grouped by section
+use similar::{DiffOp, TextDiff};
+use std::io::Write;
+
+use crate::error::{DropsError, Result};
+
+// A line added, removed or replaced by another
+enum LineChange<'a> {
+    Added(&'a str),
+    Removed(&'a str),
+    Modified(&'a str, &'a str),
+}
+
+// Write the changes from the current contents of a file to the contents about to replace it,
+// grouped under the game, date or heading each changed line belongs to, in order of
+// appearance. A removed line directly replaced by an added one is reported as modified
+pub fn write_report(name: &str, before: &str, after: &str, writer: &mut impl Write) -> Result<()> {
+    writeln!(writer, "# Changes to {}\n", name).map_err(DropsError::Write)?;
+
+    let old: Vec<&str> = before.lines().collect();
+    let new: Vec<&str> = after.lines().collect();
+    let old_sections = sections(&old);
+    let new_sections = sections(&new);
+
+    let mut groups: Vec<(&str, Vec<LineChange>)> = Vec::new();
+    let diff = TextDiff::from_slices(&old, &new);
+    for op in diff.ops() {
+        let (removed, added) = match *op {
+            DiffOp::Equal { .. } => continue,
+            DiffOp::Delete {
+                old_index, old_len, ..
+            } => (old_index..old_index + old_len, 0..0),
+            DiffOp::Insert {
+                new_index, new_len, ..
+            } => (0..0, new_index..new_index + new_len),
+            DiffOp::Replace {
+                old_index,
+                old_len,
+                new_index,
+                new_len,
+            } => (
+                old_index..old_index + old_len,
+                new_index..new_index + new_len,
+            ),
+        };
+        let paired = removed.len().min(added.len());
+        for (i, j) in removed.clone().zip(added.clone()) {
+            add(
+                &mut groups,
+                new_sections[j],
+                LineChange::Modified(old[i], new[j]),
+            );
+        }
+        // Blank lines only separate the blocks around them, so they aren't worth reporting
+        for i in removed.skip(paired).filter(|&i| !old[i].trim().is_empty()) {
+            add(&mut groups, old_sections[i], LineChange::Removed(old[i]));
+        }
+        for j in added.skip(paired).filter(|&j| !new[j].trim().is_empty()) {
+            add(&mut groups, new_sections[j], LineChange::Added(new[j]));
+        }
+    }
+
+    if groups.is_empty() {
+        writeln!(writer, "No changes.").map_err(DropsError::Write)?;
+        return Ok(());
+    }
+    for (section, changes) in groups {
+        if section.is_empty() {
+            writeln!(writer, "## (top of file)\n").map_err(DropsError::Write)?;
+        } else {
+            writeln!(writer, "## {}\n", section).map_err(DropsError::Write)?;
+        }
+        for change in changes {
+            match change {
+                LineChange::Added(line) => {
+                    writeln!(writer, "- added: {}", code(line)).map_err(DropsError::Write)?
+                }
+                LineChange::Removed(line) => {
+                    writeln!(writer, "- removed: {}", code(line)).map_err(DropsError::Write)?
+                }
+                LineChange::Modified(old, new) => {
+                    writeln!(writer, "- modified: {} → {}", code(old), code(new))
+                        .map_err(DropsError::Write)?
+                }
+            }
+        }
+        writeln!(writer).map_err(DropsError::Write)?;
+    }
+    Ok(())
+}
+
+// Add a change to the group of its section, starting a new group for a section not seen yet
+fn add<'a>(
+    groups: &mut Vec<(&'a str, Vec<LineChange<'a>>)>,
+    section: &'a str,
+    change: LineChange<'a>,
+) {
+    match groups.iter_mut().find(|(name, _)| *name == section) {
+        Some((_, changes)) => changes.push(change),
+        None => groups.push((section, vec![change])),
+    }
+}
+
+// The section each line belongs to: the closest line at or above it that starts a section,
+// which is any unindented line that isn't a list item or table row, e.g. a game name, a date
+// of the latest drops or a heading. Empty before the first of them
+fn sections<'a>(lines: &[&'a str]) -> Vec<&'a str> {
+    let mut current = "";
+    lines
+        .iter()
+        .map(|line| {
+            if starts_section(line) {
+                current = line.trim_start_matches('#').trim();
+            }
+            current
+        })
+        .collect()
+}
+
+fn starts_section(line: &str) -> bool {
+    !line.is_empty()
+        && !line.starts_with(char::is_whitespace)
+        && !line.starts_with(['-', '*', '+', '|'])
+}
+
+// A line as an inline code span, padded so backticks inside it don't end the span early
+fn code(line: &str) -> String {
+    if line.contains('`') {
+        format!("`` {} ``", line)
+    } else {
+        format!("`{}`", line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(before: &str, after: &str) -> String {
+        let mut out = Vec::new();
+        write_report("DROPS.md", before, after, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn changes_are_grouped_by_game() {
+        let before = "# Twitch Drops Campaigns\n\n## All drops\n\n\
+                      Apex\n- Season (ends in 5 days)\n  - Badge\n\n\
+                      Rust\n- Week (ends in 2 days)\n  - Hoodie\n";
+        let after = "# Twitch Drops Campaigns\n\n## All drops\n\n\
+                     Apex\n- Season (ends in 9 days)\n  - Badge\n\n\
+                     Rust\n- Week (ends in 2 days)\n  - Hoodie\n\n\
+                     Valorant\n- Launch (ends in 3 days)\n  - Spray\n";
+        assert_eq!(
+            report(before, after),
+            "# Changes to DROPS.md\n\n\
+             ## Apex\n\n\
+             - modified: `- Season (ends in 5 days)` → `- Season (ends in 9 days)`\n\n\
+             ## Valorant\n\n\
+             - added: `Valorant`\n\
+             - added: `- Launch (ends in 3 days)`\n\
+             - added: `  - Spray`\n\n"
+        );
+        assert_eq!(
+            report(after, after),
+            "# Changes to DROPS.md\n\nNo changes.\n"
+        );
+    }
+
+    #[test]
+    fn backticks_do_not_end_the_code_span() {
+        assert_eq!(code("a `b` c"), "`` a `b` c ``");
+        assert_eq!(code("plain"), "`plain`");
+    }
+}
@@ -0,0 +1,234 @@
+//! A description of the shape of a raw API response, for adapting to changes upstream
+use serde_json::{Map, Value};
+use std::io::Write;
+
+use crate::error::{DropsError, Result};
+
+// The keys the structs in api read at each level, and whether they are required
+const ENVELOPE_KEYS: &[(&str, bool)] = &[
+    ("data", false),
+    ("drops", false),
+    ("generatedAt", false),
+    ("lastUpdated", false),
+];
+const GAME_KEYS: &[(&str, bool)] = &[
+    ("gameDisplayName", true),
+    ("viewerCount", false),
+    ("updatedAt", false),
+    ("rewards", true),
+];
+const DROP_KEYS: &[(&str, bool)] = &[
+    ("name", true),
+    ("startAt", true),
+    ("endAt", true),
+    ("imageUrl", false),
+    ("detailUrl", false),
+    ("timeBasedDrops", true),
+];
+const REWARD_KEYS: &[(&str, bool)] = &[("name", true), ("requiredMinutesWatched", true)];
+
+// Write what a response looks like without deserialising it into the api structs: the top
+// level type, then the keys of the first game, campaign and reward with the type of their
+// values, flagging keys we don't read and required keys that are missing
+pub fn write_report(raw: &str, writer: &mut impl Write) -> Result<()> {
+    let value: Value = serde_json::from_str(raw).map_err(DropsError::parse("json response"))?;
+
+    let games = match &value {
+        Value::Array(games) => {
+            writeln!(writer, "top level: array of {}", games.len()).map_err(DropsError::Write)?;
+            games
+        }
+        Value::Object(envelope) => {
+            writeln!(writer, "top level: object").map_err(DropsError::Write)?;
+            write_keys(envelope, ENVELOPE_KEYS, writer)?;
+            match envelope.get("data").or_else(|| envelope.get("drops")) {
+                Some(Value::Array(games)) => {
+                    writeln!(writer, "games: array of {}", games.len())
+                        .map_err(DropsError::Write)?;
+                    games
+                }
+                _ => {
+                    writeln!(writer, "games: no data or drops array").map_err(DropsError::Write)?;
+                    return Ok(());
+                }
+            }
+        }
+        other => {
+            writeln!(writer, "top level: {}", type_name(other)).map_err(DropsError::Write)?;
+            return Ok(());
+        }
+    };
+
+    let Some(game) = first_object(games, "game", writer)? else {
+        return Ok(());
+    };
+    writeln!(writer, "game keys:").map_err(DropsError::Write)?;
+    write_keys(game, GAME_KEYS, writer)?;
+
+    let Some(drops) = nested_array(game, "rewards", writer)? else {
+        return Ok(());
+    };
+    let Some(drop) = first_object(drops, "campaign", writer)? else {
+        return Ok(());
+    };
+    writeln!(writer, "campaign keys:").map_err(DropsError::Write)?;
+    write_keys(drop, DROP_KEYS, writer)?;
+
+    let Some(rewards) = nested_array(drop, "timeBasedDrops", writer)? else {
+        return Ok(());
+    };
+    let Some(reward) = first_object(rewards, "reward", writer)? else {
+        return Ok(());
+    };
+    writeln!(writer, "reward keys:").map_err(DropsError::Write)?;
+    write_keys(reward, REWARD_KEYS, writer)?;
+    Ok(())
+}
+
+// The first element of an array, reporting when there is none or it isn't an object
+fn first_object<'a>(
+    items: &'a [Value],
+    what: &str,
+    writer: &mut impl Write,
+) -> Result<Option<&'a Map<String, Value>>> {
+    match items.first() {
+        Some(Value::Object(object)) => Ok(Some(object)),
+        Some(other) => {
+            writeln!(
+                writer,
+                "first {}: {}, not an object",
+                what,
+                type_name(other)
+            )
+            .map_err(DropsError::Write)?;
+            Ok(None)
+        }
+        None => {
+            writeln!(writer, "no {} to look into", what).map_err(DropsError::Write)?;
+            Ok(None)
+        }
+    }
+}
+
+// The array under a key, reporting whether it is there
+fn nested_array<'a>(
+    object: &'a Map<String, Value>,
+    key: &str,
+    writer: &mut impl Write,
+) -> Result<Option<&'a [Value]>> {
+    match object.get(key) {
+        Some(Value::Array(items)) => {
+            writeln!(writer, "{}: present, array of {}", key, items.len())
+                .map_err(DropsError::Write)?;
+            Ok(Some(items))
+        }
+        Some(other) => {
+            writeln!(
+                writer,
+                "{}: present, {} instead of array",
+                key,
+                type_name(other)
+            )
+            .map_err(DropsError::Write)?;
+            Ok(None)
+        }
+        None => {
+            writeln!(writer, "{}: missing", key).map_err(DropsError::Write)?;
+            Ok(None)
+        }
+    }
+}
+
+// A line per key in the order of the response, then the required keys it lacks
+fn write_keys(
+    object: &Map<String, Value>,
+    known: &[(&str, bool)],
+    writer: &mut impl Write,
+) -> Result<()> {
+    for (key, value) in object {
+        if known.iter().any(|(name, _)| name == key) {
+            writeln!(writer, "  {}: {}", key, sample(value)).map_err(DropsError::Write)?;
+        } else {
+            writeln!(writer, "  {}: {} (unexpected)", key, sample(value))
+                .map_err(DropsError::Write)?;
+        }
+    }
+    for (name, _) in known
+        .iter()
+        .filter(|(name, required)| *required && !object.contains_key(*name))
+    {
+        writeln!(writer, "  {}: missing", name).map_err(DropsError::Write)?;
+    }
+    Ok(())
+}
+
+// The type of a value, with the value itself when it is a short scalar
+fn sample(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Array(items) => format!("array of {}", items.len()),
+        Value::Object(object) => format!("object with {} keys", object.len()),
+        Value::String(text) if text.chars().count() > 40 => {
+            let short: String = text.chars().take(40).collect();
+            format!("string {:?}…", short)
+        }
+        _ => format!("{} {}", type_name(value), value),
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(raw: &str) -> String {
+        let mut out = Vec::new();
+        write_report(raw, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn unexpected_keys_are_reported_at_every_level() {
+        assert_eq!(
+            report(include_str!("../tests/fixtures/drops-unexpected.json")),
+            "top level: object\n\
+             \x20 generatedAt: string \"2026-10-15T00:00:00Z\"\n\
+             \x20 region: string \"eu\" (unexpected)\n\
+             \x20 data: array of 1\n\
+             games: array of 1\n\
+             game keys:\n\
+             \x20 gameDisplayName: string \"Rust\"\n\
+             \x20 gameBoxArtUrl: string \"https://example.com/rust.png\" (unexpected)\n\
+             \x20 rewards: array of 1\n\
+             rewards: present, array of 1\n\
+             campaign keys:\n\
+             \x20 name: string \"Twitch Drops Week\"\n\
+             \x20 startAt: string \"2026-10-10T17:00:00Z\"\n\
+             \x20 endAt: string \"2026-10-24T17:00:00Z\"\n\
+             \x20 timeBasedDrops: array of 1\n\
+             timeBasedDrops: present, array of 1\n\
+             reward keys:\n\
+             \x20 name: string \"Hoodie\"\n\
+             \x20 requiredMinutesWatched: number 120\n\
+             \x20 rarity: number 3 (unexpected)\n"
+        );
+    }
+
+    #[test]
+    fn missing_required_keys_are_reported() {
+        let raw = r#"[{"gameDisplayName": "Rust", "rewards": [{"name": "Week"}]}]"#;
+        let report = report(raw);
+        assert!(report.contains("  startAt: missing\n  endAt: missing\n"));
+        assert!(report.ends_with("timeBasedDrops: missing\n"));
+    }
+}
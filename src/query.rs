@@ -0,0 +1,330 @@
+//! Chainable filters over the fetched games for library users and the CLI flags
+use chrono::{DateTime, Duration, Utc};
+
+use crate::api::{ApiDrops, ApiGame};
+
+// A set of filters applied to the games when collected, e.g.
+// DropsQuery::new(games).game_contains("apex").min_minutes(60).collect()
+pub struct DropsQuery {
+    games: Vec<ApiGame>,
+    now: DateTime<Utc>,
+    game_contains: Option<String>,
+    games_named: Option<Vec<String>>,
+    min_minutes: Option<u16>,
+    ending_within: Option<Duration>,
+    ended_within: Option<Duration>,
+    end_after: Option<DateTime<Utc>>,
+    end_before: Option<DateTime<Utc>>,
+    with_rewards: bool,
+}
+
+impl DropsQuery {
+    pub fn new(games: Vec<ApiGame>) -> DropsQuery {
+        DropsQuery {
+            games,
+            now: Utc::now(),
+            game_contains: None,
+            games_named: None,
+            min_minutes: None,
+            ending_within: None,
+            ended_within: None,
+            end_after: None,
+            end_before: None,
+            with_rewards: false,
+        }
+    }
+
+    // The time the relative filters are measured from, the current time by default
+    pub fn now(mut self, now: DateTime<Utc>) -> DropsQuery {
+        self.now = now;
+        self
+    }
+
+    // Only keep games whose name contains the pattern, ignoring case
+    pub fn game_contains(mut self, pattern: &str) -> DropsQuery {
+        self.game_contains = Some(pattern.to_lowercase());
+        self
+    }
+
+    // Only keep the games with one of the given names, ignoring case
+    pub fn games_named(mut self, names: &[String]) -> DropsQuery {
+        self.games_named = Some(names.iter().map(|name| name.to_lowercase()).collect());
+        self
+    }
+
+    // Only keep drops with at least one reward requiring this many minutes or more
+    pub fn min_minutes(mut self, minutes: u16) -> DropsQuery {
+        self.min_minutes = Some(minutes);
+        self
+    }
+
+    // Only keep drops that are still running and end within the given time
+    pub fn ending_within(mut self, within: Duration) -> DropsQuery {
+        self.ending_within = Some(within);
+        self
+    }
+
+    // Drop the campaigns that ended longer ago than the given time. Without this, ended
+    // campaigns included in the response are kept
+    pub fn ended_within(mut self, within: Duration) -> DropsQuery {
+        self.ended_within = Some(within);
+        self
+    }
+
+    // Only keep drops ending at or after the given time
+    pub fn end_after(mut self, time: DateTime<Utc>) -> DropsQuery {
+        self.end_after = Some(time);
+        self
+    }
+
+    // Only keep drops ending strictly before the given time, so consecutive windows don't overlap
+    pub fn end_before(mut self, time: DateTime<Utc>) -> DropsQuery {
+        self.end_before = Some(time);
+        self
+    }
+
+    // Only keep drops that have at least one reward
+    pub fn with_rewards(mut self) -> DropsQuery {
+        self.with_rewards = true;
+        self
+    }
+
+    // Apply the filters. Games left without any drops by a drop filter are removed, games
+    // that had no drops to begin with only go if their name doesn't match
+    pub fn collect(self) -> Vec<ApiGame> {
+        self.collect_explained().0
+    }
+
+    // Apply the filters like collect, also returning every game and drop that was left out
+    // with the filter that removed it
+    pub fn collect_explained(self) -> (Vec<ApiGame>, Vec<Excluded>) {
+        let DropsQuery {
+            mut games,
+            now,
+            game_contains,
+            games_named,
+            min_minutes,
+            ending_within,
+            ended_within,
+            end_after,
+            end_before,
+            with_rewards,
+        } = self;
+        let mut excluded = Vec::new();
+
+        if let Some(pattern) = &game_contains {
+            games.retain(|game| {
+                let keep = game.game_display_name.to_lowercase().contains(pattern);
+                if !keep {
+                    excluded.push(Excluded::game(game, ExcludedBy::GameContains));
+                }
+                keep
+            });
+        }
+
+        if let Some(names) = &games_named {
+            games.retain(|game| {
+                let keep = names.contains(&game.game_display_name.to_lowercase());
+                if !keep {
+                    excluded.push(Excluded::game(game, ExcludedBy::GamesNamed));
+                }
+                keep
+            });
+        }
+
+        // The first filter that rejects the drop, in the order they are listed
+        let rejected_by = |drop: &ApiDrops| {
+            if min_minutes.is_some_and(|min| drop.rewards.iter().all(|r| r.minutes_required < min))
+            {
+                Some(ExcludedBy::MinMinutes)
+            } else if ending_within
+                .is_some_and(|within| drop.end_at <= now || drop.end_at > now + within)
+            {
+                Some(ExcludedBy::EndingWithin)
+            } else if ended_within.is_some_and(|within| drop.end_at <= now - within) {
+                Some(ExcludedBy::EndedWithin)
+            } else if end_after.is_some_and(|after| drop.end_at < after) {
+                Some(ExcludedBy::EndAfter)
+            } else if end_before.is_some_and(|before| drop.end_at >= before) {
+                Some(ExcludedBy::EndBefore)
+            } else if with_rewards && drop.rewards.is_empty() {
+                Some(ExcludedBy::WithRewards)
+            } else {
+                None
+            }
+        };
+        if min_minutes.is_some()
+            || ending_within.is_some()
+            || ended_within.is_some()
+            || end_after.is_some()
+            || end_before.is_some()
+            || with_rewards
+        {
+            for game in &mut games {
+                let name = &game.game_display_name;
+                game.drops.retain(|drop| match rejected_by(drop) {
+                    Some(by) => {
+                        excluded.push(Excluded {
+                            game: name.clone(),
+                            drop: Some(drop.name.clone()),
+                            by,
+                        });
+                        false
+                    }
+                    None => true,
+                });
+            }
+            games.retain(|game| {
+                let keep = !game.drops.is_empty();
+                if !keep {
+                    excluded.push(Excluded::game(game, ExcludedBy::NoDropsLeft));
+                }
+                keep
+            });
+        }
+        (games, excluded)
+    }
+}
+
+// A game, or a drop of a game, left out by a query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Excluded {
+    pub game: String,
+    // None when the whole game was left out
+    pub drop: Option<String>,
+    pub by: ExcludedBy,
+}
+
+impl Excluded {
+    fn game(game: &ApiGame, by: ExcludedBy) -> Excluded {
+        Excluded {
+            game: game.game_display_name.clone(),
+            drop: None,
+            by,
+        }
+    }
+}
+
+// The filter that left a game or drop out, named after the DropsQuery method that set it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExcludedBy {
+    GameContains,
+    GamesNamed,
+    MinMinutes,
+    EndingWithin,
+    EndedWithin,
+    EndAfter,
+    EndBefore,
+    WithRewards,
+    // Every drop of the game was left out by the drop filters
+    NoDropsLeft,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::ApiReward;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 15, 0, 0, 0).unwrap()
+    }
+
+    fn campaign(name: &str, end_at: DateTime<Utc>, rewards: &[(&str, u16)]) -> ApiDrops {
+        ApiDrops {
+            name: name.to_string(),
+            start_at: now() - Duration::days(30),
+            end_at,
+            image_url: None,
+            detail_url: None,
+            rewards: rewards
+                .iter()
+                .map(|(name, minutes)| ApiReward {
+                    name: name.to_string(),
+                    minutes_required: *minutes,
+                })
+                .collect(),
+        }
+    }
+
+    // A game with a single campaign ending at the given time
+    fn ending_at(name: &str, end_at: DateTime<Utc>) -> ApiGame {
+        ApiGame {
+            game_display_name: name.to_string(),
+            viewer_count: None,
+            updated_at: None,
+            drops: vec![campaign("Campaign", end_at, &[("Badge", 60)])],
+        }
+    }
+
+    fn names(games: &[ApiGame]) -> Vec<&str> {
+        games.iter().map(|g| g.game_display_name.as_str()).collect()
+    }
+
+    #[test]
+    fn ended_within_keeps_campaigns_that_ended_inside_the_window() {
+        let day = Duration::hours(24);
+        let games = vec![
+            ending_at("Too Old", now() - day),
+            ending_at("Just Inside", now() - day + Duration::seconds(1)),
+            ending_at("Ending Now", now()),
+            ending_at("Running", now() + day),
+        ];
+        let (kept, excluded) = DropsQuery::new(games)
+            .now(now())
+            .ended_within(day)
+            .collect_explained();
+        assert_eq!(names(&kept), ["Just Inside", "Ending Now", "Running"]);
+        assert_eq!(excluded[0].by, ExcludedBy::EndedWithin);
+        assert_eq!(excluded[0].game, "Too Old");
+    }
+
+    #[test]
+    fn end_after_is_inclusive_and_end_before_exclusive() {
+        let first = now() + Duration::days(1);
+        let second = now() + Duration::days(2);
+        let games = vec![
+            ending_at("Before", first - Duration::seconds(1)),
+            ending_at("First", first),
+            ending_at("Between", first + Duration::hours(12)),
+            ending_at("Second", second),
+        ];
+        let (kept, excluded) = DropsQuery::new(games)
+            .now(now())
+            .end_after(first)
+            .end_before(second)
+            .collect_explained();
+        assert_eq!(names(&kept), ["First", "Between"]);
+        let by: Vec<_> = excluded
+            .iter()
+            .filter(|e| e.drop.is_some())
+            .map(|e| e.by)
+            .collect();
+        assert_eq!(by, [ExcludedBy::EndAfter, ExcludedBy::EndBefore]);
+    }
+
+    #[test]
+    fn with_rewards_removes_rewardless_drops_and_emptied_games() {
+        let tomorrow = now() + Duration::days(1);
+        let mut mixed = ending_at("Mixed", tomorrow);
+        mixed.drops.push(campaign("Rewardless", tomorrow, &[]));
+        let mut empty = ending_at("Empty", tomorrow);
+        empty.drops = vec![campaign("Rewardless", tomorrow, &[])];
+
+        let (kept, excluded) = DropsQuery::new(vec![mixed, empty])
+            .with_rewards()
+            .collect_explained();
+        assert_eq!(names(&kept), ["Mixed"]);
+        assert_eq!(kept[0].drops.len(), 1);
+        assert_eq!(kept[0].drops[0].name, "Campaign");
+        let by: Vec<_> = excluded.iter().map(|e| (e.game.as_str(), e.by)).collect();
+        assert_eq!(
+            by,
+            [
+                ("Mixed", ExcludedBy::WithRewards),
+                ("Empty", ExcludedBy::WithRewards),
+                ("Empty", ExcludedBy::NoDropsLeft),
+            ]
+        );
+    }
+}
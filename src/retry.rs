@@ -0,0 +1,146 @@
+//! Exponential backoff with jitter between failed fetch attempts, waits asked for by rate
+//! limited responses, and jitter for the polling interval of watch mode
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::time::Duration;
+
+const BASE_DELAY: Duration = Duration::from_secs(1);
+const MAX_DELAY: Duration = Duration::from_secs(60);
+pub const DEFAULT_MAX_RETRY_AFTER: Duration = Duration::from_secs(300);
+
+pub struct Backoff {
+    rng: StdRng,
+    max_retry_after: Duration,
+}
+
+impl Backoff {
+    pub fn new(seed: Option<u64>) -> Backoff {
+        Backoff {
+            rng: rng(seed),
+            max_retry_after: DEFAULT_MAX_RETRY_AFTER,
+        }
+    }
+
+    // The longest Retry-After wait of a rate limited response that is honoured
+    pub fn max_retry_after(mut self, max: Duration) -> Backoff {
+        self.max_retry_after = max;
+        self
+    }
+
+    // Delay before retrying a rate limited attempt: what the API asked for up to the maximum,
+    // falling back to the usual backoff when it didn't say
+    pub fn rate_limited_delay(&mut self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        match retry_after {
+            Some(wait) => wait.min(self.max_retry_after),
+            None => self.delay(attempt),
+        }
+    }
+
+    // Delay before retry number `attempt` (starting at 0): doubles every attempt up to a cap,
+    // plus up to half of that again picked at random so clients don't retry in lockstep
+    pub fn delay(&mut self, attempt: u32) -> Duration {
+        let exponential = BASE_DELAY
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(MAX_DELAY);
+        let jitter_ms = self
+            .rng
+            .random_range(0..=exponential.as_millis() as u64 / 2);
+        exponential + Duration::from_millis(jitter_ms)
+    }
+}
+
+// Random extra delay added to every sleep between watch iterations, so instances started
+// at the same time drift apart instead of polling the API together
+pub struct PollJitter {
+    rng: StdRng,
+    max: Duration,
+}
+
+impl PollJitter {
+    pub fn new(max: Duration, seed: Option<u64>) -> PollJitter {
+        PollJitter {
+            rng: rng(seed),
+            max,
+        }
+    }
+
+    // The interval plus a random delay of up to the maximum jitter
+    pub fn interval(&mut self, interval: Duration) -> Duration {
+        let jitter_ms = self.rng.random_range(0..=self.max.as_millis() as u64);
+        interval + Duration::from_millis(jitter_ms)
+    }
+}
+
+// A fixed seed makes the delays reproducible, otherwise the RNG is seeded from OS entropy
+fn rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_os_rng(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delays(seed: Option<u64>) -> Vec<Duration> {
+        let mut backoff = Backoff::new(seed);
+        (0..5).map(|attempt| backoff.delay(attempt)).collect()
+    }
+
+    fn intervals(seed: Option<u64>) -> Vec<Duration> {
+        let mut jitter = PollJitter::new(Duration::from_secs(5), seed);
+        (0..5)
+            .map(|_| jitter.interval(Duration::from_secs(60)))
+            .collect()
+    }
+
+    #[test]
+    fn a_seed_makes_the_delays_reproducible() {
+        assert_eq!(delays(Some(7)), delays(Some(7)));
+        assert_ne!(delays(Some(7)), delays(Some(8)));
+    }
+
+    #[test]
+    fn delays_double_up_to_the_cap_with_at_most_half_again_of_jitter() {
+        let mut backoff = Backoff::new(Some(1));
+        for attempt in 0..10 {
+            let exponential = BASE_DELAY.saturating_mul(2u32.pow(attempt)).min(MAX_DELAY);
+            let delay = backoff.delay(attempt);
+            assert!(delay >= exponential && delay <= exponential + exponential / 2);
+        }
+    }
+
+    #[test]
+    fn poll_jitter_stays_within_the_maximum() {
+        let mut jitter = PollJitter::new(Duration::from_secs(5), Some(3));
+        for _ in 0..20 {
+            let interval = jitter.interval(Duration::from_secs(60));
+            assert!(interval >= Duration::from_secs(60) && interval <= Duration::from_secs(65));
+        }
+    }
+
+    #[test]
+    fn a_seed_makes_the_poll_intervals_reproducible() {
+        assert_eq!(intervals(Some(3)), intervals(Some(3)));
+        assert_ne!(intervals(Some(3)), intervals(Some(4)));
+    }
+
+    #[test]
+    fn rate_limited_waits_are_honoured_up_to_the_maximum() {
+        let mut backoff = Backoff::new(Some(1)).max_retry_after(Duration::from_secs(60));
+        let asked = Some(Duration::from_secs(30));
+        assert_eq!(
+            backoff.rate_limited_delay(3, asked),
+            Duration::from_secs(30)
+        );
+        let too_long = Some(Duration::from_secs(600));
+        assert_eq!(
+            backoff.rate_limited_delay(0, too_long),
+            Duration::from_secs(60)
+        );
+        // Without a Retry-After the usual backoff applies
+        let delay = backoff.rate_limited_delay(0, None);
+        assert!(delay >= BASE_DELAY && delay <= BASE_DELAY * 3 / 2);
+    }
+}
@@ -0,0 +1,73 @@
+//! Heuristic classification of rewards by what they are, based on their names
+
+// Kind of a reward, declared in the order the kinds are listed with --by-reward-type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RewardKind {
+    Emote,
+    Badge,
+    InGameItem,
+    Other,
+}
+
+// Words in a reward name that give away its kind, checked in this order
+const KEYWORDS: &[(RewardKind, &[&str])] = &[
+    (RewardKind::Emote, &["emote", "emotes", "emoji"]),
+    (RewardKind::Badge, &["badge", "badges", "flair"]),
+    (
+        RewardKind::InGameItem,
+        &[
+            "skin", "skins", "item", "items", "pack", "bundle", "case", "chest", "crate", "weapon",
+            "sticker", "charm", "banner", "spray", "outfit", "cosmetic", "mount", "pet", "loot",
+            "drop", "token", "tokens", "currency", "coins", "gems",
+        ],
+    ),
+];
+
+// Guess the kind of a reward from the words in its name, matched case-insensitively
+pub fn classify_reward(name: &str) -> RewardKind {
+    let words: Vec<String> = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    KEYWORDS
+        .iter()
+        .find(|(_, keywords)| words.iter().any(|word| keywords.contains(&word.as_str())))
+        .map_or(RewardKind::Other, |(kind, _)| *kind)
+}
+
+// The form of a reward name used to tell whether two rewards are the same: trimmed, with runs
+// of whitespace collapsed to a single space and, with fold_case, lowercased. Only used for
+// comparing, the names are written as the API gave them
+pub fn canonical_reward_name(name: &str, fold_case: bool) -> String {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    if fold_case { name.to_lowercase() } else { name }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_names_ignore_whitespace_and_optionally_case() {
+        assert_eq!(canonical_reward_name("  Gold   Skin ", false), "Gold Skin");
+        assert_eq!(canonical_reward_name("Gold\tSkin", false), "Gold Skin");
+        assert_ne!(canonical_reward_name("GOLD skin", false), "Gold Skin");
+        assert_eq!(canonical_reward_name("GOLD  skin", true), "gold skin");
+    }
+
+    #[test]
+    fn rewards_are_classified_by_the_words_in_their_name() {
+        assert_eq!(classify_reward("Hype Emote"), RewardKind::Emote);
+        assert_eq!(classify_reward("SEASON 3 BADGE"), RewardKind::Badge);
+        assert_eq!(
+            classify_reward("Golden-Skin Bundle"),
+            RewardKind::InGameItem
+        );
+        assert_eq!(classify_reward("Exclusive Title"), RewardKind::Other);
+        // Whole words only, "Badger" is not a badge
+        assert_eq!(classify_reward("Badger"), RewardKind::Other);
+        // Emotes are checked first
+        assert_eq!(classify_reward("Emote Pack"), RewardKind::Emote);
+    }
+}
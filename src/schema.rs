@@ -0,0 +1,190 @@
+//! Validation of the raw API response against the bundled JSON Schema
+//!
+//! Only the keywords used by schema/drops.schema.json are supported: type, required,
+//! properties, items, minimum, maximum and the date-time format
+use serde_json::Value;
+
+use crate::error::{DropsError, Result};
+
+const DROPS_SCHEMA: &str = include_str!("../schema/drops.schema.json");
+
+// A single place where the response does not match the schema
+struct Violation {
+    pointer: String,
+    message: String,
+}
+
+// Check a raw response against the drops schema, failing with every violation found
+pub fn validate(raw: &str) -> Result<()> {
+    let schema: Value =
+        serde_json::from_str(DROPS_SCHEMA).map_err(DropsError::parse("bundled schema"))?;
+    let instance: Value = serde_json::from_str(raw).map_err(DropsError::parse("json response"))?;
+
+    let mut violations = Vec::new();
+    check(&schema, &instance, String::new(), &mut violations);
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    let details: Vec<String> = violations
+        .iter()
+        .map(|v| {
+            let pointer = if v.pointer.is_empty() {
+                "/"
+            } else {
+                &v.pointer
+            };
+            format!("  {}: {}", pointer, v.message)
+        })
+        .collect();
+    Err(DropsError::Schema(details.join("\n")))
+}
+
+// Recursively check a value, collecting violations with the JSON pointer to where they are
+fn check(schema: &Value, value: &Value, pointer: String, violations: &mut Vec<Violation>) {
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.iter().any(|name| has_type(value, name)) {
+            violations.push(Violation {
+                pointer,
+                message: format!(
+                    "expected {}, found {}",
+                    allowed.join(" or "),
+                    type_name(value)
+                ),
+            });
+            // Nothing below makes sense for a value of the wrong type
+            return;
+        }
+    }
+
+    if let Some(Value::Array(required)) = schema.get("required")
+        && let Value::Object(map) = value
+    {
+        for field in required.iter().filter_map(Value::as_str) {
+            if !map.contains_key(field) {
+                violations.push(Violation {
+                    pointer: pointer.clone(),
+                    message: format!("missing required field `{}`", field),
+                });
+            }
+        }
+    }
+
+    if let Some(Value::Object(properties)) = schema.get("properties")
+        && let Value::Object(map) = value
+    {
+        for (field, field_schema) in properties {
+            if let Some(field_value) = map.get(field) {
+                let child = format!("{}/{}", pointer, escape_pointer(field));
+                check(field_schema, field_value, child, violations);
+            }
+        }
+    }
+
+    if let Some(items) = schema.get("items")
+        && let Value::Array(elements) = value
+    {
+        for (i, element) in elements.iter().enumerate() {
+            check(items, element, format!("{}/{}", pointer, i), violations);
+        }
+    }
+
+    if let Some(number) = value.as_f64() {
+        if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64)
+            && number < minimum
+        {
+            violations.push(Violation {
+                pointer: pointer.clone(),
+                message: format!("{} is less than the minimum of {}", number, minimum),
+            });
+        }
+        if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64)
+            && number > maximum
+        {
+            violations.push(Violation {
+                pointer: pointer.clone(),
+                message: format!("{} is more than the maximum of {}", number, maximum),
+            });
+        }
+    }
+
+    if schema.get("format").and_then(Value::as_str) == Some("date-time")
+        && let Some(text) = value.as_str()
+        && chrono::DateTime::parse_from_rfc3339(text).is_err()
+    {
+        violations.push(Violation {
+            pointer,
+            message: format!("`{}` is not an RFC 3339 date-time", text),
+        });
+    }
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        _ => false,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+// Escape a field name for use in a JSON pointer (RFC 6901)
+fn escape_pointer(field: &str) -> String {
+    field.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_valid_response_passes() {
+        validate(include_str!("../tests/fixtures/drops-valid.json")).unwrap();
+    }
+
+    #[test]
+    fn an_invalid_response_reports_every_violation() {
+        let Err(DropsError::Schema(details)) =
+            validate(include_str!("../tests/fixtures/drops-invalid.json"))
+        else {
+            panic!("expected a schema error");
+        };
+        assert_eq!(
+            details.lines().collect::<Vec<_>>(),
+            [
+                "  /0/rewards/0: missing required field `endAt`",
+                "  /0/rewards/0/startAt: `next tuesday` is not an RFC 3339 date-time",
+                "  /0/rewards/0/timeBasedDrops/0/requiredMinutesWatched: -5 is less than the minimum of 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn a_value_of_the_wrong_type_is_reported_once() {
+        let Err(DropsError::Schema(details)) = validate(r#"{"data": []}"#) else {
+            panic!("expected a schema error");
+        };
+        assert_eq!(details, "  /: expected array, found object");
+    }
+}
@@ -0,0 +1,95 @@
+//! shields.io endpoint badge showing the number of active drop campaigns
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::api::ApiGame;
+use crate::error::{DropsError, Result};
+use crate::json;
+use crate::summary::Summary;
+
+// The JSON read by https://shields.io/badges/endpoint-badge
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Endpoint {
+    schema_version: u8,
+    label: &'static str,
+    message: String,
+    color: &'static str,
+}
+
+// Green while campaigns are running, orange when any of them ends within 24 hours and grey
+// when there are none
+pub fn endpoint(games: &[ApiGame], now: DateTime<Utc>) -> Endpoint {
+    let summary = Summary::of(games, now);
+    let color = if summary.campaigns == 0 {
+        "lightgrey"
+    } else if summary.ending_within_24h > 0 {
+        "orange"
+    } else {
+        "green"
+    };
+    Endpoint {
+        schema_version: 1,
+        label: "drops",
+        message: format!("{} active", summary.campaigns),
+        color,
+    }
+}
+
+// Render the badge JSON, pretty-printed or on a single line
+pub fn render(games: &[ApiGame], now: DateTime<Utc>, pretty: bool) -> Result<String> {
+    let mut rendered = json::to_string(&endpoint(games, now), pretty)
+        .map_err(DropsError::serialise("shields endpoint"))?;
+    rendered.push('\n');
+    Ok(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{ApiDrops, ApiReward};
+    use chrono::{Duration, TimeZone};
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 15, 0, 0, 0).unwrap()
+    }
+
+    fn game(ends_in: Duration) -> ApiGame {
+        ApiGame {
+            game_display_name: "Game".to_string(),
+            viewer_count: None,
+            updated_at: None,
+            drops: vec![ApiDrops {
+                name: "Campaign".to_string(),
+                start_at: now() - Duration::days(3),
+                end_at: now() + ends_in,
+                image_url: None,
+                detail_url: None,
+                rewards: vec![ApiReward {
+                    name: "Badge".to_string(),
+                    minutes_required: 60,
+                }],
+            }],
+        }
+    }
+
+    #[test]
+    fn the_badge_has_the_endpoint_shape() {
+        let games = vec![game(Duration::days(5)), game(Duration::days(9))];
+        assert_eq!(
+            render(&games, now(), false).unwrap(),
+            "{\"schemaVersion\":1,\"label\":\"drops\",\"message\":\"2 active\",\"color\":\"green\"}\n"
+        );
+    }
+
+    #[test]
+    fn the_colour_shows_campaigns_ending_soon() {
+        let soon = vec![game(Duration::days(5)), game(Duration::hours(6))];
+        assert_eq!(endpoint(&soon, now()).color, "orange");
+        let none = endpoint(&[], now());
+        assert_eq!(
+            (none.message.as_str(), none.color),
+            ("0 active", "lightgrey")
+        );
+    }
+}
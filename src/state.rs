@@ -0,0 +1,196 @@
+//! State remembered between runs: the campaigns seen while watching and the freshness of
+//! the last written list
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use crate::atomic;
+use crate::error::{DropsError, Result};
+
+// Identifiers of the drop campaigns that were active during the recent iterations
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeenDrops {
+    ids: BTreeSet<String>,
+    // How many iterations in a row each remembered ID has been missing for, absent for the
+    // IDs that were active in the last one
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    missing_runs: BTreeMap<String, u32>,
+}
+
+impl SeenDrops {
+    // Load the seen set from disk, starting empty if the file does not exist yet
+    pub fn load(path: &Path) -> Result<SeenDrops> {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(DropsError::parse(path.display().to_string())),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(SeenDrops::default()),
+            Err(err) => Err(DropsError::io("read", path)(err)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let contents = serde_json::to_string(self).map_err(DropsError::serialise("seen drops"))?;
+        atomic::write_file(path, contents.as_bytes())
+    }
+
+    // Add the currently active drops to the seen set and return the ones not seen before.
+    // Drops missing for forget_after iterations in a row are forgotten, so they notify again
+    // if they ever return
+    pub fn update(&mut self, current: BTreeSet<String>, forget_after: u32) -> BTreeSet<String> {
+        let new = current.difference(&self.ids).cloned().collect();
+        for id in self.ids.difference(&current) {
+            *self.missing_runs.entry(id.clone()).or_default() += 1;
+        }
+        self.missing_runs.retain(|id, _| !current.contains(id));
+        self.ids.extend(current);
+        self.prune(forget_after);
+        new
+    }
+
+    // Forget every drop that isn't in the current set, however recently it went missing,
+    // returning how many were forgotten
+    pub fn forget_inactive(&mut self, current: &BTreeSet<String>) -> usize {
+        let before = self.ids.len();
+        self.ids.retain(|id| current.contains(id));
+        self.missing_runs.clear();
+        before - self.ids.len()
+    }
+
+    // Forget the drops missing for at least forget_after iterations in a row, returning how
+    // many were forgotten
+    fn prune(&mut self, forget_after: u32) -> usize {
+        let before = self.ids.len();
+        let ids = &mut self.ids;
+        self.missing_runs.retain(|id, runs| {
+            let forget = *runs >= forget_after;
+            if forget {
+                ids.remove(id);
+            }
+            !forget
+        });
+        before - self.ids.len()
+    }
+}
+
+// When the API generated the data behind the last written list, for --write-if-newer
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LastWritten {
+    pub generated_at: DateTime<Utc>,
+}
+
+impl LastWritten {
+    // Load the last generation time, None if nothing was written with one yet
+    pub fn load(path: &Path) -> Result<Option<LastWritten>> {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map(Some)
+                .map_err(DropsError::parse(path.display().to_string())),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(DropsError::io("read", path)(err)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let contents =
+            serde_json::to_string(self).map_err(DropsError::serialise("last written time"))?;
+        atomic::write_file(path, contents.as_bytes())
+    }
+}
+
+// A drop campaign that was active when the changelog was last written
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveDrop {
+    pub id: String,
+    pub game: String,
+    pub name: String,
+    pub start_at: DateTime<Utc>,
+    pub end_at: DateTime<Utc>,
+}
+
+// The campaigns active during the last run, for --log-file
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ActiveDrops {
+    pub drops: Vec<ActiveDrop>,
+}
+
+impl ActiveDrops {
+    // Load the campaigns from disk, starting empty if the file does not exist yet
+    pub fn load(path: &Path) -> Result<ActiveDrops> {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(DropsError::parse(path.display().to_string())),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(ActiveDrops::default()),
+            Err(err) => Err(DropsError::io("read", path)(err)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let contents =
+            serde_json::to_string(self).map_err(DropsError::serialise("active drops"))?;
+        atomic::write_file(path, contents.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(ids: &[&str]) -> BTreeSet<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn update_returns_the_drops_not_seen_before() {
+        let mut seen = SeenDrops::default();
+        assert_eq!(seen.update(ids(&["a", "b"]), 1), ids(&["a", "b"]));
+        assert_eq!(seen.update(ids(&["a", "b", "c"]), 1), ids(&["c"]));
+    }
+
+    #[test]
+    fn missing_drops_are_kept_until_forget_after_runs() {
+        let mut seen = SeenDrops::default();
+        seen.update(ids(&["a", "b"]), 3);
+        seen.update(ids(&["a"]), 3);
+        seen.update(ids(&["a"]), 3);
+        // Missing for two runs, still remembered so it doesn't notify again
+        assert!(seen.update(ids(&["a", "b"]), 3).is_empty());
+
+        seen.update(ids(&["a"]), 3);
+        seen.update(ids(&["a"]), 3);
+        seen.update(ids(&["a"]), 3);
+        // Missing for three runs, forgotten and new again
+        assert_eq!(seen.update(ids(&["a", "b"]), 3), ids(&["b"]));
+    }
+
+    #[test]
+    fn forget_inactive_removes_drops_missing_for_fewer_runs() {
+        let mut seen = SeenDrops::default();
+        seen.update(ids(&["a", "b", "c"]), 5);
+        seen.update(ids(&["a"]), 5);
+        assert_eq!(seen.forget_inactive(&ids(&["a"])), 2);
+        assert_eq!(seen.ids, ids(&["a"]));
+        assert!(seen.missing_runs.is_empty());
+    }
+
+    #[test]
+    fn seen_drops_round_trip_through_a_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("seen.json");
+        assert!(SeenDrops::load(&path).unwrap().ids.is_empty());
+
+        let mut seen = SeenDrops::default();
+        seen.update(ids(&["a", "b"]), 2);
+        seen.update(ids(&["a"]), 2);
+        seen.save(&path).unwrap();
+
+        let loaded = SeenDrops::load(&path).unwrap();
+        assert_eq!(loaded.ids, ids(&["a", "b"]));
+        assert_eq!(loaded.missing_runs.get("b"), Some(&1));
+    }
+}
@@ -0,0 +1,29 @@
+//! Headline numbers for the active drop campaigns
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::api::ApiGame;
+
+const ENDING_SOON_HOURS: i64 = 24;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Summary {
+    pub campaigns: usize,
+    pub games: usize,
+    pub ending_within_24h: usize,
+}
+
+impl Summary {
+    pub fn of(games: &[ApiGame], now: DateTime<Utc>) -> Summary {
+        let soon = now + Duration::hours(ENDING_SOON_HOURS);
+        let drops = games.iter().flat_map(|game| &game.drops);
+        Summary {
+            campaigns: drops.clone().count(),
+            games: games.iter().filter(|game| !game.drops.is_empty()).count(),
+            ending_within_24h: drops
+                .filter(|drop| drop.end_at > now && drop.end_at <= soon)
+                .count(),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! OpenTelemetry spans for the fetch, parse and render phases, exported over OTLP with the
+//! `otel` feature. Without the feature the spans compile down to calling the wrapped code
+#[cfg(feature = "otel")]
+use opentelemetry::trace::{Span as _, Tracer};
+#[cfg(feature = "otel")]
+use opentelemetry::{KeyValue, global};
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::trace::SdkTracerProvider;
+
+// A span being recorded, which attributes can be added to
+pub struct Span {
+    #[cfg(feature = "otel")]
+    inner: global::BoxedSpan,
+}
+
+impl Span {
+    pub fn set(&mut self, key: &'static str, value: usize) {
+        #[cfg(feature = "otel")]
+        self.inner
+            .set_attribute(KeyValue::new(key, i64::try_from(value).unwrap_or(i64::MAX)));
+        #[cfg(not(feature = "otel"))]
+        let _ = (key, value);
+    }
+}
+
+// Run the code in a span with the given name, ended when the code returns
+pub fn span<T>(name: &'static str, f: impl FnOnce(&mut Span) -> T) -> T {
+    let mut span = Span {
+        #[cfg(feature = "otel")]
+        inner: global::tracer("twitch-drops-list").start(name),
+    };
+    #[cfg(not(feature = "otel"))]
+    let _ = name;
+    f(&mut span)
+}
+
+// Flushes the spans still queued for export when dropped at the end of main
+pub struct Guard {
+    #[cfg(feature = "otel")]
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if let Some(provider) = self.provider.take()
+            && let Err(err) = provider.shutdown()
+        {
+            eprintln!("failed to export traces: {}", err);
+        }
+    }
+}
+
+// Set up the OTLP exporter. The endpoint, headers and service name come from the standard
+// OTEL_* environment variables, and OTEL_SDK_DISABLED=true turns the export off
+pub fn init() -> Guard {
+    #[cfg(feature = "otel")]
+    {
+        if std::env::var("OTEL_SDK_DISABLED").is_ok_and(|v| v.eq_ignore_ascii_case("true")) {
+            return Guard { provider: None };
+        }
+        let exporter = match opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .build()
+        {
+            Ok(exporter) => exporter,
+            Err(err) => {
+                eprintln!("failed to set up trace export: {}", err);
+                return Guard { provider: None };
+            }
+        };
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .build();
+        global::set_tracer_provider(provider.clone());
+        Guard {
+            provider: Some(provider),
+        }
+    }
+    #[cfg(not(feature = "otel"))]
+    Guard {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "otel")]
+    use opentelemetry::Value;
+    #[cfg(feature = "otel")]
+    use opentelemetry_sdk::trace::InMemorySpanExporter;
+
+    #[test]
+    fn spans_return_what_the_code_returns() {
+        let value = span("parse", |span| {
+            span.set("games", usize::MAX);
+            42
+        });
+        assert_eq!(value, 42);
+    }
+
+    #[cfg(feature = "otel")]
+    #[test]
+    fn spans_are_exported_with_their_attributes() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        global::set_tracer_provider(provider.clone());
+
+        span("render", |span| {
+            span.set("games", 3);
+            span.set("bytes", 1024);
+        });
+        provider.force_flush().unwrap();
+
+        let spans = exporter.get_finished_spans().unwrap();
+        let render = spans.iter().find(|s| s.name == "render").unwrap();
+        let attribute = |key: &str| {
+            render
+                .attributes
+                .iter()
+                .find(|kv| kv.key.as_str() == key)
+                .map(|kv| kv.value.clone())
+        };
+        assert_eq!(attribute("games"), Some(Value::I64(3)));
+        assert_eq!(attribute("bytes"), Some(Value::I64(1024)));
+    }
+}
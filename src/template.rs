@@ -0,0 +1,141 @@
+//! Small templates for customising how reward lines are rendered
+use crate::error::{DropsError, Result};
+use crate::locale::Translations;
+
+// Placeholders that can be used in a reward template
+const PLACEHOLDERS: &[&str] = &["name", "minutes", "hours"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Name,
+    Minutes,
+    Hours,
+}
+
+// A parsed reward line template such as "{name} ({minutes} minutes watched)".
+// `{{` and `}}` write literal braces
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RewardTemplate {
+    parts: Vec<Part>,
+}
+
+impl RewardTemplate {
+    pub fn parse(template: &str) -> Result<RewardTemplate> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => placeholder.push(c),
+                            None => {
+                                return Err(DropsError::Template(format!(
+                                    "unclosed `{{` in reward format `{}`",
+                                    template
+                                )));
+                            }
+                        }
+                    }
+                    let part = match placeholder.as_str() {
+                        "name" => Part::Name,
+                        "minutes" => Part::Minutes,
+                        "hours" => Part::Hours,
+                        _ => {
+                            return Err(DropsError::Template(format!(
+                                "unknown placeholder `{{{}}}` in reward format, expected one of: {}",
+                                placeholder,
+                                PLACEHOLDERS
+                                    .iter()
+                                    .map(|p| format!("{{{}}}", p))
+                                    .collect::<Vec<_>>()
+                                    .join(", ")
+                            )));
+                        }
+                    };
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(part);
+                }
+                '}' => {
+                    return Err(DropsError::Template(format!(
+                        "unmatched `}}` in reward format `{}`",
+                        template
+                    )));
+                }
+                _ => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(RewardTemplate { parts })
+    }
+
+    // Fill in the template for a reward, `name`, `minutes` and `hours` should already be
+    // formatted
+    pub fn render(&self, name: &str, minutes: &str, hours: &str) -> String {
+        let mut rendered = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => rendered.push_str(text),
+                Part::Name => rendered.push_str(name),
+                Part::Minutes => rendered.push_str(minutes),
+                Part::Hours => rendered.push_str(hours),
+            }
+        }
+        rendered
+    }
+}
+
+// Whole hours without a fraction, otherwise one decimal place
+pub fn format_hours(minutes: u64, text: &Translations) -> String {
+    if minutes.is_multiple_of(60) {
+        text.number(minutes / 60)
+    } else {
+        text.decimal(minutes as f64 / 60.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale::Locale;
+
+    #[test]
+    fn hours_use_the_decimal_separator_of_the_language() {
+        assert_eq!(format_hours(120, Locale::En.translations()), "2");
+        assert_eq!(format_hours(90, Locale::En.translations()), "1.5");
+        assert_eq!(format_hours(90, Locale::De.translations()), "1,5");
+    }
+
+    #[test]
+    fn templates_fill_in_the_placeholders() {
+        let template = RewardTemplate::parse("{name}: {minutes} min, {hours} h {{x}}").unwrap();
+        assert_eq!(
+            template.render("Badge", "90", "1.5"),
+            "Badge: 90 min, 1.5 h {x}"
+        );
+    }
+
+    #[test]
+    fn unknown_placeholders_are_rejected() {
+        assert!(RewardTemplate::parse("{nope}").is_err());
+        assert!(RewardTemplate::parse("oops }").is_err());
+    }
+}
@@ -0,0 +1,243 @@
+//! Interactive terminal browser over the fetched campaigns, behind the `tui` feature
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::api::{ApiDrops, ApiGame};
+use crate::error::{DropsError, Result};
+use crate::markdown::{self, RenderOptions};
+
+// Which list the arrow keys move through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pane {
+    Games,
+    Drops,
+}
+
+struct Browser<'a> {
+    games: Vec<ApiGame>,
+    options: &'a RenderOptions,
+    filter: String,
+    sort_by_end: bool,
+    pane: Pane,
+    game_state: ListState,
+    drop_state: ListState,
+}
+
+// Show the games and their drops until the user quits with Esc or Ctrl-C
+pub fn run(games: Vec<ApiGame>, options: &RenderOptions) -> Result<()> {
+    let mut browser = Browser {
+        games,
+        options,
+        filter: String::new(),
+        sort_by_end: false,
+        pane: Pane::Games,
+        game_state: ListState::default(),
+        drop_state: ListState::default(),
+    };
+    let mut terminal = ratatui::init();
+    let result = browser.event_loop(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+impl Browser<'_> {
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            self.clamp_selection();
+            terminal
+                .draw(|frame| self.draw(frame))
+                .map_err(|source| DropsError::Terminal { op: "draw", source })?;
+            if let Event::Key(key) =
+                event::read().map_err(|source| DropsError::Terminal { op: "read", source })?
+                && key.kind == KeyEventKind::Press
+                && !self.handle_key(key)
+            {
+                return Ok(());
+            }
+        }
+    }
+
+    // Apply a key press, returning false once the browser should close
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return false,
+            KeyCode::Char('c') if ctrl => return false,
+            KeyCode::Char('s') if ctrl => self.sort_by_end = !self.sort_by_end,
+            KeyCode::Char(c) => {
+                self.filter.push(c);
+                self.game_state.select(Some(0));
+                self.drop_state.select(Some(0));
+            }
+            KeyCode::Backspace => {
+                self.filter.pop();
+            }
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.pane = match self.pane {
+                    Pane::Games => Pane::Drops,
+                    Pane::Drops => Pane::Games,
+                }
+            }
+            KeyCode::Up => self.focused_state().select_previous(),
+            KeyCode::Down => self.focused_state().select_next(),
+            _ => {}
+        }
+        if self.pane == Pane::Games && matches!(key.code, KeyCode::Up | KeyCode::Down) {
+            self.drop_state.select(Some(0));
+        }
+        true
+    }
+
+    fn focused_state(&mut self) -> &mut ListState {
+        match self.pane {
+            Pane::Games => &mut self.game_state,
+            Pane::Drops => &mut self.drop_state,
+        }
+    }
+
+    // The games matching the filter, by name or by their soonest ending drop with Ctrl-S
+    fn visible_games(&self) -> Vec<&ApiGame> {
+        let filter = self.filter.to_lowercase();
+        let mut games: Vec<&ApiGame> = self
+            .games
+            .iter()
+            .filter(|game| game.game_display_name.to_lowercase().contains(&filter))
+            .collect();
+        if self.sort_by_end {
+            games.sort_by_key(|game| game.drops.iter().map(|drop| drop.end_at).min());
+        }
+        games
+    }
+
+    // The drops of a game, soonest ending first
+    fn sorted_drops(game: &ApiGame) -> Vec<&ApiDrops> {
+        let mut drops: Vec<&ApiDrops> = game.drops.iter().collect();
+        drops.sort_by_key(|drop| drop.end_at);
+        drops
+    }
+
+    // Keep both selections inside their lists after filtering shrank them
+    fn clamp_selection(&mut self) {
+        let game_count = self.visible_games().len();
+        clamp(&mut self.game_state, game_count);
+        let drop_count = self
+            .game_state
+            .selected()
+            .and_then(|i| self.visible_games().get(i).map(|game| game.drops.len()))
+            .unwrap_or(0);
+        clamp(&mut self.drop_state, drop_count);
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, help] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)])
+                .areas(main);
+        let [drops_area, detail_area] =
+            Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(right);
+
+        let games = self.visible_games();
+        let selected_game = self
+            .game_state
+            .selected()
+            .and_then(|i| games.get(i))
+            .copied();
+        let drops = selected_game.map(Browser::sorted_drops).unwrap_or_default();
+        let selected_drop = self
+            .drop_state
+            .selected()
+            .and_then(|i| drops.get(i))
+            .copied();
+        let highlight = Style::new().add_modifier(Modifier::REVERSED);
+
+        let game_items: Vec<String> = games
+            .iter()
+            .map(|game| game.game_display_name.clone())
+            .collect();
+        let title = format!(
+            " Games ({}) filter: {}{} ",
+            games.len(),
+            self.filter,
+            if self.sort_by_end { " [by end]" } else { "" }
+        );
+        let game_list = List::new(game_items)
+            .block(pane_block(title, self.pane == Pane::Games))
+            .highlight_style(highlight);
+
+        let drop_items: Vec<String> = drops
+            .iter()
+            .map(|drop| {
+                format!(
+                    "{} ({})",
+                    drop.name,
+                    markdown::ends_in_days(drop.end_at, self.options.now, self.options.text)
+                )
+            })
+            .collect();
+        let drop_list = List::new(drop_items)
+            .block(pane_block(" Drops ".into(), self.pane == Pane::Drops))
+            .highlight_style(highlight);
+
+        let detail = Paragraph::new(
+            selected_drop
+                .map(|drop| self.detail(drop))
+                .unwrap_or_default(),
+        )
+        .block(Block::bordered().title(" Rewards "))
+        .wrap(Wrap { trim: false });
+
+        frame.render_stateful_widget(game_list, left, &mut self.game_state);
+        frame.render_stateful_widget(drop_list, drops_area, &mut self.drop_state);
+        frame.render_widget(detail, detail_area);
+        frame.render_widget(
+            Line::from(
+                "type to filter  Tab switch pane  Up/Down select  Ctrl-S sort by end  Esc quit",
+            ),
+            help,
+        );
+    }
+
+    // The deadline and rewards of a drop
+    fn detail(&self, drop: &ApiDrops) -> Vec<Line<'static>> {
+        let text = self.options.text;
+        let mut lines = vec![
+            Line::from(format!(
+                "{}, {}",
+                markdown::ends_in_days(drop.end_at, self.options.now, text),
+                drop.end_at.format("%Y-%m-%d %H:%M UTC")
+            )),
+            Line::from(""),
+        ];
+        lines.extend(drop.rewards.iter().map(|reward| {
+            Line::from(format!(
+                "{} ({})",
+                reward.name,
+                markdown::minutes_watched(reward.minutes_required, text)
+            ))
+        }));
+        lines
+    }
+}
+
+fn pane_block(title: String, focused: bool) -> Block<'static> {
+    let block = Block::bordered().title(title);
+    if focused {
+        block.border_style(Style::new().add_modifier(Modifier::BOLD))
+    } else {
+        block
+    }
+}
+
+fn clamp(state: &mut ListState, len: usize) {
+    match (state.selected(), len) {
+        (_, 0) => state.select(None),
+        (None, _) => state.select(Some(0)),
+        (Some(i), _) if i >= len => state.select(Some(len - 1)),
+        _ => {}
+    }
+}
@@ -0,0 +1,23 @@
+// Kept in its own test binary because it moves the process-wide temporary directory, which
+// would otherwise break every other test creating temporary files
+use std::fs;
+use twitch_drops_list::atomic;
+
+#[test]
+fn the_temporary_file_is_created_next_to_the_target_not_in_tmpdir() {
+    let dir = tempfile::tempdir().unwrap();
+    // A temporary directory that doesn't exist, so anything created there fails
+    let elsewhere = dir.path().join("missing");
+    tempfile::env::override_temp_dir(&elsewhere).unwrap();
+    assert_eq!(tempfile::env::temp_dir(), elsewhere);
+
+    let output = dir.path().join("output");
+    fs::create_dir(&output).unwrap();
+    let path = output.join("DROPS.md");
+    atomic::write_file(&path, b"first").unwrap();
+    atomic::write_file(&path, b"second").unwrap();
+    atomic::write_file_gzip(&output.join("DROPS.json.gz"), b"[]").unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"second");
+    assert_eq!(fs::read_dir(&output).unwrap().count(), 2);
+    assert!(!elsewhere.exists());
+}